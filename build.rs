@@ -7,11 +7,25 @@
 //! Cargo re-run the build script whenever `memory.x` is changed,
 //! updating `memory.x` ensures a rebuild of the application with the
 //! new memory settings.
+//!
+//! It also turns the metadata of the non-secure firmware into the tables of
+//! the secure runtime. The metadata is read from `HARM_METADATA_DIR` (the
+//! `metadata` directory by default), and the tables are written to `OUT_DIR`
+//! where `src/runtime/mod.rs` and `c_lib/nonsecure_entry/nsc.c` include them,
+//! so several firmware can be built from one checkout. The metadata is
+//! linted first, and every mistake found fails the build with a message
+//! naming the offending object instead of a hard fault on the board.
+//!
+//! The sandbox banks come from `sandbox.yaml` in the same directory, they are
+//! handed to the runtime and to the MPU configuration of `mpu_config.c`, along
+//! with the optional windows the non-secure stacks and heap are drawn from.
+//! Along with the regions of `memory.x`, they describe the memory map the
+//! runtime derives its TrustZone configuration from.
 
 use std::env;
-use std::fs::{File, read_dir};
+use std::fs::{self, File, read_dir};
 use std::io::{Write, Error};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 use std::ffi::OsString;
 use cc::Build;
@@ -21,18 +35,28 @@ struct RelocInfo {
     src_offset: u16,
     dst_index: u16,
     dst_offset: u16,
+    /// name of a `RelocKind` variant, metadata without it only holds wide branches
+    #[serde(default = "RelocInfo::default_kind")]
+    kind: String,
 }
 
 impl RelocInfo {
+    fn default_kind() -> String {
+        "B_W".to_string()
+    }
+
     pub fn get_reloc_string(&self) -> String {
-        format!("Branch(0x{:x}, {}, 0x{:x}),", self.src_offset, self.dst_index, self.dst_offset)
+        format!("Branch {{ src_offset: 0x{:x}, dst_object: {}, dst_offset: 0x{:x}, kind: RelocKind::{} }},",
+                self.src_offset, self.dst_index, self.dst_offset, self.kind)
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 enum ObjectKind {
     Function,
+    BasicBlock,
     VectorTable,
+    Data,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,15 +66,18 @@ struct ObjectInfo {
     kind: ObjectKind,
     reloc_items: Vec::<RelocInfo>,
     address: u32,
-    size: u16, 
-    isr: u16,   
+    /// wider than the `u16` of the runtime so that oversized objects are reported by name
+    size: u32,
+    isr: u16,
 }
 
 impl ObjectInfo {
     pub fn get_object_string(&self, index: usize, adjtbl_from: usize) -> String {
         let kind_str = match self.kind {
             ObjectKind::Function => "ObjectKind::Function",
+            ObjectKind::BasicBlock => "ObjectKind::BasicBlock",
             ObjectKind::VectorTable => "ObjectKind::VectorTable",
+            ObjectKind::Data => "ObjectKind::Data",
         };
 
         let reloc_str = if !self.reloc_items.is_empty() {
@@ -72,14 +99,343 @@ struct CallsiteInfo {
     offsets: Vec::<u16>,
 }
 
-fn generate_callsite_metadata() -> Result<(), Error> {
-    let callsites_str = include_str!("metadata/callsites.yaml");
-    let callsites: Vec::<CallsiteInfo> = serde_yaml::from_str(callsites_str).expect("failed to parse callsite metadata");
-    let mut rettbl_file = File::create("src/secure_rt_core/ret_tbl.rs")?;
-    let mut nsc_file = File::create("c_lib/nonsecure_entry/callsite_tbl.h")?;
+#[derive(Debug, Serialize, Deserialize)]
+struct BankInfo {
+    base: usize,
+    size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HeapInfo {
+    symbol: usize,
+    base: usize,
+    size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StacksInfo {
+    base: usize,
+    size: usize,
+    main: usize,
+    #[serde(default)]
+    process: usize,
+    heap: Option<HeapInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SandboxInfo {
+    banks: Vec::<BankInfo>,
+    stacks: Option<StacksInfo>,
+}
+
+/// Names of the `RelocKind` variants with the length of their instruction
+const RELOC_KINDS: [(&str, u32); 14] = [
+    ("B_W", 4), ("B_T1", 2), ("B_T2", 2), ("B_T3", 4), ("B_T4", 4), ("BL", 4), ("BLX", 4),
+    ("CBZ", 2), ("ADR_T1", 2), ("ADR_W", 4), ("LDR_T1", 2), ("LDR_W", 4), ("MOVW", 4), ("MOVT", 4),
+];
+
+/// Relocation kinds allowed to refer to a `Data` object (`RelocKind::is_data_reference()`)
+const DATA_REFERENCES: [&str; 6] = ["ADR_T1", "ADR_W", "LDR_T1", "LDR_W", "MOVW", "MOVT"];
+
+/// Size of the branch appended to an object falling through to the next one (`FALL_THROUGH_SIZE`)
+const FALL_THROUGH_SIZE: usize = 4;
+
+/// Number of sandbox banks of the runtime (`NUM_OF_BANKS`)
+const NUM_OF_BANKS: usize = 2;
+
+/// Granularity of the SAU and MPU regions (`REGION_ALIGN`)
+const REGION_ALIGN: usize = 32;
+
+/// Alignment of the non-secure stack pointers and their limits (`STACK_ALIGN`)
+const STACK_ALIGN: usize = 8;
+
+/// Check the sandbox banks the way `check_banks()` does at boot, except for the SAU configuration
+fn lint_sandbox(sandbox: &SandboxInfo) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if sandbox.banks.len() != NUM_OF_BANKS {
+        errors.push(format!("{} sandbox banks, the runtime needs {}", sandbox.banks.len(), NUM_OF_BANKS));
+    }
+    for (i, bank) in sandbox.banks.iter().enumerate() {
+        if bank.size == 0 || bank.base % REGION_ALIGN != 0 || bank.size % REGION_ALIGN != 0 {
+            errors.push(format!("sandbox bank {} (0x{:x}, 0x{:x}) is not a non-empty multiple of {} bytes", i, bank.base, bank.size, REGION_ALIGN));
+        }
+        for (j, other) in sandbox.banks[.. i].iter().enumerate() {
+            if bank.base < other.base + other.size && other.base < bank.base + bank.size {
+                errors.push(format!("sandbox banks {} and {} overlap", j, i));
+            }
+        }
+    }
+
+    // the rest of `StackPolicy::check()` needs the SAU configuration
+    if let Some(stacks) = &sandbox.stacks {
+        let mut windows = vec![("stack window", stacks.base, stacks.size)];
+        windows.extend(stacks.heap.iter().map(|heap| ("heap window", heap.base, heap.size)));
+
+        for (i, &(name, base, size)) in windows.iter().enumerate() {
+            if size == 0 || base % STACK_ALIGN != 0 || size % STACK_ALIGN != 0 {
+                errors.push(format!("{} (0x{:x}, 0x{:x}) is not a non-empty multiple of {} bytes", name, base, size, STACK_ALIGN));
+            }
+            if sandbox.banks.iter().any(|bank| base < bank.base + bank.size && bank.base < base + size) {
+                errors.push(format!("{} (0x{:x}, 0x{:x}) overlaps the sandbox", name, base, size));
+            }
+            if windows[.. i].iter().any(|&(_, other, other_size)| base < other + other_size && other < base + size) {
+                errors.push(format!("{} (0x{:x}, 0x{:x}) overlaps the stack window", name, base, size));
+            }
+        }
+        if stacks.main == 0 || stacks.main % STACK_ALIGN != 0 || stacks.process % STACK_ALIGN != 0 {
+            errors.push(format!("stack sizes 0x{:x} and 0x{:x} are not multiples of {} bytes, or the main stack is empty", stacks.main, stacks.process, STACK_ALIGN));
+        }
+        if stacks.main + stacks.process > stacks.size {
+            errors.push(format!("stacks of 0x{:x} bytes do not fit the stack window of 0x{:x}", stacks.main + stacks.process, stacks.size));
+        }
+        if let Some(heap) = &stacks.heap {
+            let inside = |base: usize, size: usize| heap.symbol + 4 > base && heap.symbol < base + size;
+            if heap.symbol % 4 != 0 {
+                errors.push(format!("heap start variable 0x{:x} is not aligned", heap.symbol));
+            }
+            if windows.iter().any(|&(_, base, size)| inside(base, size)) || sandbox.banks.iter().any(|bank| inside(bank.base, bank.size)) {
+                errors.push(format!("heap start variable 0x{:x} is inside a window or the sandbox", heap.symbol));
+            }
+        }
+    }
+
+    errors
+}
+
+/// Check the metadata the way `validate_metadata()` does at boot, returns every mistake found
+fn lint_metadata(objects: &[ObjectInfo], callsites: &[CallsiteInfo], sandbox_size: usize) -> Vec<String> {
+    let mut errors = Vec::new();
+    let name = |i: usize| objects.get(i).map_or("?", |o| o.name.as_str());
+
+    match objects.first() {
+        Some(ObjectInfo { kind: ObjectKind::VectorTable, .. }) => {},
+        Some(first) => errors.push(format!("object 0 ({}) is not the vector table", first.name)),
+        None => errors.push("no objects".to_string()),
+    }
+
+    let mut isrs = Vec::new();
+    for (i, obj) in objects.iter().enumerate() {
+        if obj.index != i {
+            errors.push(format!("object {} ({}) has index {}", i, obj.name, obj.index));
+        }
+        if i != 0 {
+            if let ObjectKind::VectorTable = obj.kind {
+                errors.push(format!("object {} ({}) is a second vector table", i, obj.name));
+            }
+        }
+        if obj.size > u16::MAX as u32 {
+            errors.push(format!("object {} ({}) is {} bytes, more than {}", i, obj.name, obj.size, u16::MAX));
+        }
+        if matches!(obj.kind, ObjectKind::Data) && !obj.reloc_items.is_empty() {
+            errors.push(format!("object {} ({}) is data but has reloc items", i, obj.name));
+        }
+
+        for (j, reloc) in obj.reloc_items.iter().enumerate() {
+            let at = format!("reloc item at 0x{:x} of object {} ({})", reloc.src_offset, i, obj.name);
+            let fall_through = j + 1 == obj.reloc_items.len() && reloc.src_offset as u32 == obj.size;
+            match RELOC_KINDS.iter().find(|(kind, _)| *kind == reloc.kind) {
+                Some((_, size)) if !fall_through && reloc.src_offset as u32 + size > obj.size => {
+                    errors.push(format!("{} is past the end of the object", at));
+                },
+                Some(_) => {},
+                None => errors.push(format!("{} has unknown kind {}", at, reloc.kind)),
+            }
+            match objects.get(reloc.dst_index as usize) {
+                None => errors.push(format!("{} targets object {} which does not exist", at, reloc.dst_index)),
+                Some(ObjectInfo { kind: ObjectKind::VectorTable, .. }) => {
+                    errors.push(format!("{} targets the vector table", at));
+                },
+                Some(ObjectInfo { kind: ObjectKind::Data, name, .. }) if fall_through || !DATA_REFERENCES.contains(&reloc.kind.as_str()) => {
+                    errors.push(format!("{} branches to data object {} ({})", at, reloc.dst_index, name));
+                },
+                Some(dst) if reloc.dst_offset as u32 >= dst.size => {
+                    errors.push(format!("{} targets offset 0x{:x} past the end of object {} ({})",
+                                        at, reloc.dst_offset, reloc.dst_index, dst.name));
+                },
+                Some(_) => {},
+            }
+        }
+
+        if obj.isr != 0 {
+            if let ObjectKind::Function = obj.kind {} else {
+                errors.push(format!("object {} ({}) handles isr {} but is not a function", i, obj.name, obj.isr));
+            }
+            match objects.first() {
+                Some(vt) if obj.isr as u32 >= vt.size / 4 => {
+                    errors.push(format!("object {} ({}) handles isr {} beyond the vector table", i, obj.name, obj.isr));
+                },
+                _ => {},
+            }
+            isrs.push((obj.isr, i));
+        }
+    }
+
+    isrs.sort();
+    for pair in isrs.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            errors.push(format!("objects {} ({}) and {} ({}) both handle isr {}",
+                                pair[0].1, name(pair[0].1), pair[1].1, name(pair[1].1), pair[0].0));
+        }
+    }
+
+    let mut by_address = objects.iter().enumerate().collect::<Vec<_>>();
+    by_address.sort_by_key(|(_, obj)| obj.address);
+    for pair in by_address.windows(2) {
+        let ((i, a), (j, b)) = (pair[0], pair[1]);
+        if a.address as u64 + a.size as u64 > b.address as u64 {
+            errors.push(format!("object {} ({}) at 0x{:x} overlaps object {} ({}) at 0x{:x}",
+                                i, a.name, a.address, j, b.name, b.address));
+        }
+    }
+
+    for cs in callsites.iter() {
+        match objects.get(cs.caller as usize) {
+            Some(ObjectInfo { kind: ObjectKind::VectorTable, .. }) | Some(ObjectInfo { kind: ObjectKind::Data, .. }) | None => {
+                errors.push(format!("callsites of object {} which is not code", cs.caller));
+            },
+            Some(caller) => {
+                for offset in cs.offsets.iter().filter(|&&offset| offset as u32 > caller.size) {
+                    errors.push(format!("callsite at 0x{:x} is past the end of object {} ({})", offset, cs.caller, caller.name));
+                }
+            },
+        }
+    }
+
+    // worst case of the runtime: every object padded to its alignment (128 bytes for the vector table),
+    // the data objects in an area of their own, a multiple of the region granularity
+    let worst_size = |obj: &ObjectInfo| {
+        let fall_through = obj.reloc_items.last().map_or(false, |r| r.src_offset as u32 == obj.size);
+        let align = match obj.kind {
+            ObjectKind::VectorTable => 128,
+            ObjectKind::Data => 8,
+            _ if obj.address & 3 == 0 => 4,
+            _ => 2,
+        };
+        obj.size as usize + if fall_through { FALL_THROUGH_SIZE } else { 0 } + align - 1
+    };
+    let is_data = |obj: &&ObjectInfo| matches!(obj.kind, ObjectKind::Data);
+    let data: usize = objects.iter().filter(is_data).map(worst_size).sum();
+    let code: usize = objects.iter().filter(|obj| !is_data(obj)).map(worst_size).sum();
+    let needed = code + (data + REGION_ALIGN - 1) / REGION_ALIGN * REGION_ALIGN;
+    if needed > sandbox_size {
+        errors.push(format!("objects need up to {} bytes of the sandbox, the smallest bank holds {}", needed, sandbox_size));
+    }
+
+    errors
+}
+
+/// Directory holding `objects.yaml` and `callsites.yaml`, relative paths start from the crate root
+fn metadata_dir() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=HARM_METADATA_DIR");
+    let dir = env::var_os("HARM_METADATA_DIR").map_or_else(|| PathBuf::from("metadata"), PathBuf::from);
+    PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap()).join(dir)
+}
+
+/// Read a metadata file, rebuilding whenever it changes
+fn read_metadata(path: &Path) -> String {
+    println!("cargo:rerun-if-changed={}", path.display());
+    fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+}
+
+/// Parse a number of `memory.x`, in hexadecimal or decimal with an optional `K` or `M` suffix
+fn parse_memory_number(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let (digits, scale) = match text.chars().last()? {
+        'K' | 'k' => (&text[.. text.len() - 1], 1024),
+        'M' | 'm' => (&text[.. text.len() - 1], 1024 * 1024),
+        _ => (text, 1),
+    };
+    let value = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(value * scale)
+}
+
+/// `(name, origin, length)` of the regions of the `MEMORY` block of `memory.x`
+fn parse_memory_regions(memory_x: &str) -> Vec<(String, usize, usize)> {
+    let block = memory_x.split("MEMORY").nth(1).and_then(|rest| rest.split('}').next()).expect("no MEMORY block in memory.x");
+
+    block.lines().filter_map(|line| {
+        let line = line.split("/*").next().unwrap();
+        let (name, attributes) = line.split_once(':')?;
+        let mut origin = None;
+        let mut length = None;
+        for attribute in attributes.split(',') {
+            let (key, value) = attribute.split_once('=')?;
+            match key.trim() {
+                "ORIGIN" | "org" | "o" => origin = parse_memory_number(value),
+                "LENGTH" | "len" | "l" => length = parse_memory_number(value),
+                _ => {},
+            }
+        }
+        let name = name.split('(').next().unwrap().trim().to_string();
+        match (origin, length) {
+            (Some(origin), Some(length)) => Some((name, origin, length)),
+            _ => panic!("cannot parse the {} region of memory.x", name),
+        }
+    }).collect()
+}
+
+/// Write the regions of `memory.x` as `(base, size)` constants, the TrustZone configuration is derived from them
+fn generate_memory_map(memory_x: &str, out: &Path) -> Result<(), Error> {
+    let regions = parse_memory_regions(memory_x);
+    let mut rs_file = File::create(out.join("memory.rs"))?;
+
+    for required in ["FLASH", "FLASH_NSC", "RAM"].iter() {
+        if !regions.iter().any(|(name, _, _)| name == required) {
+            panic!("memory.x has no {} region", required);
+        }
+    }
+    for (name, origin, length) in regions.iter() {
+        rs_file.write_all(format!("pub const {}: (usize, usize) = (0x{:x}, 0x{:x});\n", name, origin, length).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn generate_sandbox_config(sandbox: &SandboxInfo, out: &Path) -> Result<(), Error> {
+    let mut rs_file = File::create(out.join("sandbox.rs"))?;
+    let mut h_file = File::create(out.join("sandbox.h"))?;
+
+    rs_file.write_all("use secure_rt_core::NUM_OF_BANKS;\n\n".as_bytes())?;
+    rs_file.write_all("pub const SANDBOX_BANKS: [(usize, usize); NUM_OF_BANKS] = [".as_bytes())?;
+    for bank in sandbox.banks.iter() {
+        rs_file.write_all(format!("\n\t(0x{:x}, 0x{:x}),", bank.base, bank.size).as_bytes())?;
+    }
+    rs_file.write_all("\n];\n".as_bytes())?;
+
+    let stacks = match &sandbox.stacks {
+        Some(stacks) => {
+            let heap = match &stacks.heap {
+                Some(heap) => format!("Some(secure_rt_core::HeapPolicy {{ symbol: 0x{:x}, window: (0x{:x}, 0x{:x}) }})", heap.symbol, heap.base, heap.size),
+                None => "None".to_string(),
+            };
+            format!("Some(secure_rt_core::StackPolicy {{\n\twindow: (0x{:x}, 0x{:x}),\n\tmain_size: 0x{:x},\n\tprocess_size: 0x{:x},\n\theap: {},\n}})",
+                    stacks.base, stacks.size, stacks.main, stacks.process, heap)
+        },
+        None => "None".to_string(),
+    };
+    rs_file.write_all(format!("\npub const STACKS: Option<secure_rt_core::StackPolicy> = {};\n", stacks).as_bytes())?;
+
+    // the MPU region covers all banks at once
+    let base = sandbox.banks.iter().map(|bank| bank.base).min().unwrap();
+    let end = sandbox.banks.iter().map(|bank| bank.base + bank.size).max().unwrap();
+    h_file.write_all("#ifndef SANDBOX_H\n".as_bytes())?;
+    h_file.write_all("#define SANDBOX_H\n".as_bytes())?;
+    h_file.write_all(format!("\n#define SANDBOX_BASE 0x{:x}\n", base).as_bytes())?;
+    h_file.write_all(format!("#define SANDBOX_LIMIT 0x{:x}\n", end - 1).as_bytes())?;
+    h_file.write_all("#endif /* SANDBOX_H */\n".as_bytes())?;
+
+    Ok(())
+}
+
+fn generate_callsite_metadata(callsites: &[CallsiteInfo], out: &Path) -> Result<(), Error> {
+    let mut rettbl_file = File::create(out.join("ret_tbl.rs"))?;
     let mut n_callsites = 0usize;
 
-    rettbl_file.write_all("use super::objects::Callsite;\n".as_bytes())?;
+    rettbl_file.write_all("use secure_rt_core::objects::Callsite;\n".as_bytes())?;
     rettbl_file.write_all("\n#[no_mangle]\n".as_bytes())?;
     rettbl_file.write_all("pub static CALLSITE_TBL: [Callsite; NUM_OF_CALLSITES] = [".as_bytes())?;
     for cs in callsites.iter() {
@@ -91,45 +447,32 @@ fn generate_callsite_metadata() -> Result<(), Error> {
     rettbl_file.write_all("\n];\n\n".as_bytes())?;
     rettbl_file.write_all(format!("pub const NUM_OF_CALLSITES: usize = {};", n_callsites).as_bytes())?;
 
-    nsc_file.write_all("#ifndef CALLSITE_TBL_H\n".as_bytes())?;
-    nsc_file.write_all("#define CALLSITE_TBL_H\n".as_bytes())?;
-    nsc_file.write_all(format!("\n#define CALLSITE_TABLE_SIZE {}\n\n", n_callsites).as_bytes())?;
-    nsc_file.write_all("#endif /* CALLSITE_TBL_H */".as_bytes())?;
-
     Ok(())
 }
 
-fn generate_object_metadata() -> Result<(), Error> {
-    let objects_str = include_str!("metadata/objects.yaml");
-
-    // parse objects
-    let objects: Vec::<ObjectInfo> = serde_yaml::from_str(objects_str).expect("failed to parse metadata");
+fn generate_object_metadata(objects: &[ObjectInfo], out: &Path) -> Result<(), Error> {
     let n_objs = format!("pub const NUM_OF_OBJECTS: usize = {};\n\n", objects.len());
-    let mut obj_file = File::create("src/secure_rt_core/obj_tbl.rs")?;
-    let mut adj_file = File::create("src/secure_rt_core/adj_tbl.rs")?;
-    let mut dptbl_file = File::create("c_lib/nonsecure_entry/dispatch_tbl.h")?;
+    let mut obj_file = File::create(out.join("obj_tbl.rs"))?;
+    let mut adj_file = File::create(out.join("adj_tbl.rs"))?;
+    let mut dptbl_file = File::create(out.join("dispatch_tbl.h"))?;
     let mut reloc_offset = 0usize;
     let mut obj_index = 0usize;
     let mut vectors = Vec::<&ObjectInfo>::new();
 
     dptbl_file.write_all("#ifndef DISPATCH_TBL_H\n".as_bytes())?;
     dptbl_file.write_all("#define DISPATCH_TBL_H\n".as_bytes())?;
-    dptbl_file.write_all(format!("\n#define DISPATCH_TABLE_SIZE {}\n", objects.len()).as_bytes())?;
-    dptbl_file.write_all("#define DISPATCH_MAGIC 0x10000000\n".as_bytes())?;
+    dptbl_file.write_all("\n#define DISPATCH_MAGIC 0x10000000\n".as_bytes())?;
     dptbl_file.write_all("#define DISPATCH_INDEX_BITS 12\n".as_bytes())?;
     dptbl_file.write_all("#endif /* DISPATCH_TBL_H */\n".as_bytes())?;
 
-    obj_file.write_all("use super::objects::{Object, ObjectKind};\n".as_bytes())?;
-    // obj_file.write_all("use super::adj_tbl::BRANCHES;\n".as_bytes())?;
-    obj_file.write_all("use core::mem::MaybeUninit;\n".as_bytes())?;
+    obj_file.write_all("use secure_rt_core::objects::{Object, ObjectKind};\n\n".as_bytes())?;
     obj_file.write_all(n_objs.as_bytes())?;
     obj_file.write_all("\n#[no_mangle]\n".as_bytes())?;
-    // obj_file.write_all("pub static mut DISPATCH_TBL: MaybeUninit::<[u32; NUM_OF_OBJECTS]> = MaybeUninit::<[u32; NUM_OF_OBJECTS]>::uninit();\n".as_bytes())?;
     obj_file.write_all("pub static mut DISPATCH_TBL: [u32; NUM_OF_OBJECTS] = [0u32; NUM_OF_OBJECTS];\n".as_bytes())?;
     obj_file.write_all("\n#[no_mangle]\n".as_bytes())?;
     obj_file.write_all("pub static OBJECTS: [ObjectKind; NUM_OF_OBJECTS] = [".as_bytes())?;
 
-    adj_file.write_all("use super::adjustment::Branch;\n\n".as_bytes())?;
+    adj_file.write_all("use secure_rt_core::adjustment::{Branch, RelocKind};\n\n".as_bytes())?;
     adj_file.write_all("#[no_mangle]\n".as_bytes())?;
     adj_file.write_all("pub static BRANCHES: [Branch; NUM_OF_BRANCHES] = [".as_bytes())?;
 
@@ -174,8 +517,30 @@ fn generate_object_metadata() -> Result<(), Error> {
 
 
 fn main() -> Result<(), Error>{
-    generate_object_metadata()?;
-    generate_callsite_metadata()?;
+    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let metadata = metadata_dir();
+    let objects_str = read_metadata(&metadata.join("objects.yaml"));
+    let objects: Vec::<ObjectInfo> = serde_yaml::from_str(&objects_str).expect("failed to parse metadata");
+    let callsites_str = read_metadata(&metadata.join("callsites.yaml"));
+    let callsites: Vec::<CallsiteInfo> = serde_yaml::from_str(&callsites_str).expect("failed to parse callsite metadata");
+
+    let sandbox_str = read_metadata(&metadata.join("sandbox.yaml"));
+    let sandbox: SandboxInfo = serde_yaml::from_str(&sandbox_str).expect("failed to parse sandbox configuration");
+
+    let mut errors = lint_sandbox(&sandbox);
+    let smallest_bank = sandbox.banks.iter().map(|bank| bank.size).min().unwrap_or(0);
+    errors.extend(lint_metadata(&objects, &callsites, smallest_bank));
+    if !errors.is_empty() {
+        for error in errors.iter() {
+            println!("cargo:warning={}", error);
+        }
+        panic!("{} error(s) in the metadata at {}", errors.len(), metadata.display());
+    }
+
+    generate_object_metadata(&objects, out)?;
+    generate_callsite_metadata(&callsites, out)?;
+    generate_sandbox_config(&sandbox, out)?;
+    generate_memory_map(include_str!("memory.x"), out)?;
 
     let c_entry = read_dir("c_lib/nonsecure_entry").unwrap().filter_map(|f| {
         f.ok().and_then(|e| {
@@ -186,7 +551,7 @@ fn main() -> Result<(), Error>{
             }
         })
     }).collect::<Vec<_>>();
-    Build::new().files(&c_entry).flag("-mcmse").compile("libnsc.a");
+    Build::new().files(&c_entry).include(out).flag("-mcmse").compile("libnsc.a");
 
     Build::new()
         .define("CPU_LPC55S69JBD100", None)
@@ -197,11 +562,11 @@ fn main() -> Result<(), Error>{
         .include("c_lib/lpc55s69/board")
         .include("c_lib/lpc55s69/device")
         .include("c_lib/lpc55s69/drivers")
+        .include(out)
         .file("c_lib/lpc55s69/board/board.c")
         .file("c_lib/lpc55s69/board/clock_config.c")
         .file("c_lib/lpc55s69/board/peripherals.c")
         .file("c_lib/lpc55s69/board/pin_mux.c")
-        .file("c_lib/lpc55s69/board/tzm_config.c")
         .file("c_lib/lpc55s69/board/mpu_config.c")
         .file("c_lib/lpc55s69/device/system_LPC55S69_cm33_core0.c")
         .file("c_lib/lpc55s69/drivers/fsl_clock.c")
@@ -225,7 +590,6 @@ fn main() -> Result<(), Error>{
 
     // Put `memory.x` in our output directory and ensure it's
     // on the linker search path.
-    let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
     File::create(out.join("memory.x"))
         .unwrap()
         .write_all(include_bytes!("memory.x"))
@@ -236,11 +600,12 @@ fn main() -> Result<(), Error>{
 
 
     // By default, Cargo will re-run a build script whenever
-    // any file in the project changes. By specifying `memory.x`
-    // here, we ensure the build script is only re-run when
-    // `memory.x` is changed.
+    // any file in the project changes. By listing `memory.x`, the
+    // C sources and the metadata files, we ensure the build script
+    // is only re-run when one of them is changed.
     println!("cargo:rerun-if-changed=memory.x");
-    println!("cargo:rerun-if-changed=src/nonsecure_entry/*");
+    println!("cargo:rerun-if-changed=c_lib/nonsecure_entry");
+    println!("cargo:rerun-if-changed=c_lib/lpc55s69");
     println!("cargo:rustc-link-arg=-Wl,--cmse-implib");
     println!("cargo:rustc-link-arg=-Wl,--out-implib={}", out.join("libnsclib.o").display());
 
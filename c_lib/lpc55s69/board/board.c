@@ -40,6 +40,36 @@ uint32_t get_next_random_number(void)
     return data;
 }
 
+
+/* Initial stacks of the non-secure world, a limit of 0 leaves the stack unguarded */
+void BOARD_InitStacksNS(uint32_t msp, uint32_t msplim, uint32_t psp, uint32_t psplim)
+{
+    __TZ_set_MSPLIM_NS(msplim);
+    __TZ_set_MSP_NS(msp);
+    __TZ_set_PSPLIM_NS(psplim);
+    __TZ_set_PSP_NS(psp);
+}
+
+
+/* Stack pointer of the non-secure world, the process stack if `process` is set */
+uint32_t BOARD_GetStackNS(uint32_t process)
+{
+    return process ? __TZ_get_PSP_NS() : __TZ_get_MSP_NS();
+}
+
+
+/* Entered from the HardFaultTrampoline of cortex-m-rt, with the frame it picked from the secure stacks in r0 and EXC_RETURN still in lr */
+__attribute__((naked)) void HardFault(void)
+{
+    __asm volatile(
+        "  .syntax unified                \n"
+        "  .extern secure_rt_hard_fault   \n"
+        "                                 \n"
+        "  mov    r1, lr                  \n"
+        "  b      secure_rt_hard_fault    \n"
+    );
+}
+
 #if 0
 /* Initialize debug console. */
 void BOARD_InitDebugConsole(void)
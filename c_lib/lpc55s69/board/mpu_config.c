@@ -33,8 +33,9 @@ void BOARD_InitMPU(void)
 	MPU_NS->RBAR = (STACK_HEAP_BASE_NS & MPU_RBAR_BASE_Msk) | NON_SHAREABLE | RW_P_U | EXEC_NEVER;
 	MPU_NS->RLAR = (STACK_HEAP_LIMIT_NS & MPU_RLAR_LIMIT_Msk) | ((0 << MPU_RLAR_AttrIndx_Pos) & MPU_RLAR_AttrIndx_Msk) | REGION_ENABLE;
 
+	/* Sandbox, nothing runs from it until the secure runtime gives region 2 and 3 to its banks */
 	MPU_NS->RNR = 2;
-	MPU_NS->RBAR = (SANDBOX_BASE & MPU_RBAR_BASE_Msk) | NON_SHAREABLE | RO_P_U;
+	MPU_NS->RBAR = (SANDBOX_BASE & MPU_RBAR_BASE_Msk) | NON_SHAREABLE | RO_P_U | EXEC_NEVER;
 	MPU_NS->RLAR = (SANDBOX_LIMIT & MPU_RLAR_LIMIT_Msk) | ((0 << MPU_RLAR_AttrIndx_Pos) & MPU_RLAR_AttrIndx_Msk) | REGION_ENABLE;
 
 	for (i = 3; i < mpu_regions; i++) {
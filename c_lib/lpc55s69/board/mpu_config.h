@@ -17,9 +17,8 @@
 #define STACK_HEAP_BASE_NS	0x20000000UL
 #define STACK_HEAP_LIMIT_NS	0x2000FFFFUL
 
-#define SANDBOX_BASE		0x2001a000
-#define SANDBOX_LIMIT		(0x2001a000 + 0x2a000)
-#define SANDBOX_SIZE		(SANDBOX_LIMIT - SANDBOX_BASE)
+/* SANDBOX_BASE and SANDBOX_LIMIT, generated by build.rs from sandbox.yaml */
+#include "sandbox.h"
 
 #define PROGRAM_FLASH_BASE_S		0x10000000UL
 #define PROGRAM_FLASH_LIMIT_S		0x1001FFFFUL
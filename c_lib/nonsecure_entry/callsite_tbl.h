@@ -1,6 +0,0 @@
-#ifndef CALLSITE_TBL_H
-#define CALLSITE_TBL_H
-
-#define CALLSITE_TABLE_SIZE 0
-
-#endif /* CALLSITE_TBL_H */
\ No newline at end of file
@@ -1,8 +0,0 @@
-#ifndef DISPATCH_TBL_H
-#define DISPATCH_TBL_H
-
-#define DISPATCH_TABLE_SIZE 0
-#define DISPATCH_MAGIC 0x10000000
-#define DISPATCH_INDEX_BITS 12
-
-#endif /* DISPATCH_TBL_H */
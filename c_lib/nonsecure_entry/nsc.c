@@ -2,10 +2,12 @@
 
 #include "tz_veneer.h"
 #include "dispatch_tbl.h"
-#include "callsite_tbl.h"
 
-__attribute__((used)) static const uint32_t __dispatch_tbl_sz = DISPATCH_TABLE_SIZE;
-__attribute__((used)) static const uint32_t __callsite_tbl_sz = CALLSITE_TABLE_SIZE;
+/* Tables of the running firmware, published by the secure runtime before it boots the normal world */
+extern const uint32_t *NSC_DISPATCH_TBL;
+extern const uint32_t NSC_DISPATCH_TBL_SZ;
+extern const uint32_t *NSC_CALLSITE_TBL;
+extern const uint32_t NSC_CALLSITE_TBL_SZ;
 
 
 __attribute__((used, noreturn)) static void __panic(void)
@@ -18,21 +20,24 @@ void secure_fn_return(void)
 {
     __asm volatile(
         "  .syntax unified                \n"
-        "  .extern CALLSITE_TBL           \n"
-        "  .extern DISPATCH_TBL           \n"
+        "  .extern NSC_CALLSITE_TBL       \n"
+        "  .extern NSC_CALLSITE_TBL_SZ    \n"
+        "  .extern NSC_DISPATCH_TBL       \n"
         "  .extern __panic                \n"
         "                                 \n"
         "  push   {r0-r2}                 \n"
         "  ubfx   r0, lr, #1, #27         \n"
-        "  ldr    r1, =__callsite_tbl_sz  \n"
+        "  ldr    r1, =NSC_CALLSITE_TBL_SZ\n"
         "  ldr    r1, [r1]                \n"
         "  cmp    r0, r1                  \n"
         "  bge    __panic                 \n"
-        "  ldr    r1, =CALLSITE_TBL       \n"
+        "  ldr    r1, =NSC_CALLSITE_TBL   \n"
+        "  ldr    r1, [r1]                \n"
         "  ldr    r1, [r1, r0, lsl #2]    \n"
         "  uxth   r0, r1                  \n"
         "  lsr    r1, r1, #16             \n"
-        "  ldr    r2, =DISPATCH_TBL       \n"
+        "  ldr    r2, =NSC_DISPATCH_TBL   \n"
+        "  ldr    r2, [r2]                \n"
         "  ldr    r2, [r2, r1, lsl #2]    \n"
         "  add    lr, r2, r0              \n"
         "  pop    {r0-r2}                 \n"
@@ -45,8 +50,8 @@ void secure_indirect_call(void)
 {
     __asm volatile(
         "  .syntax unified                \n"
-        "  .extern DISPATCH_TBL           \n"
-        "  .extern __dispatch_tbl_sz      \n"
+        "  .extern NSC_DISPATCH_TBL       \n"
+        "  .extern NSC_DISPATCH_TBL_SZ    \n"
         "  .extern __panic                \n"
         "                                 \n"
         "  push   {r0-r1}                 \n"
@@ -55,15 +60,42 @@ void secure_indirect_call(void)
         "  cmp    r1, r0                  \n"
         "  bne    __panic                 \n"
         "  ubfx   r0, r12, #16, %1        \n"
-        "  ldr    r1, =__dispatch_tbl_sz  \n"
+        "  ldr    r1, =NSC_DISPATCH_TBL_SZ\n"
         "  ldr    r1, [r1]                \n"
         "  cmp    r0, r1                  \n"
         "  bge    __panic                 \n"
-        "  ldr    r1, =DISPATCH_TBL       \n"
+        "  ldr    r1, =NSC_DISPATCH_TBL   \n"
+        "  ldr    r1, [r1]                \n"
         "  ldr    r1, [r1, r0, lsl #2]    \n"
         "  mov    r12, r1                 \n"
         "  pop    {r0-r1}                 \n"
         "  bxns   r12                     \n"
         :: "i"(DISPATCH_MAGIC), "i"(DISPATCH_INDEX_BITS)
     );
-}
\ No newline at end of file
+}
+
+
+void PendSV_hook0(void)
+{
+    __asm volatile(
+        "  .syntax unified                \n"
+        "  .extern secure_rt_pendsv_hook  \n"
+        "                                 \n"
+        "  push   {r0, lr}                \n"
+        "  mov    r1, lr                  \n"
+        "  tst    r0, #4                  \n"
+        "  ite    eq                      \n"
+        "  mrseq  r0, msp_ns              \n"
+        "  mrsne  r0, psp_ns              \n"
+        "  bl     secure_rt_pendsv_hook   \n"
+        "  mov    lr, r0                  \n"
+        "  pop    {r0, r1}                \n"
+        "  bic    lr, lr, #1              \n"
+        "  mov    r1, lr                  \n"
+        "  mov    r2, lr                  \n"
+        "  mov    r3, lr                  \n"
+        "  mov    r12, lr                 \n"
+        "  msr    apsr_nzcvq, lr          \n"
+        "  bxns   lr                      \n"
+    );
+}
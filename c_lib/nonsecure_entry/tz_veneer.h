@@ -12,6 +12,8 @@ NONSECURE_ENTRY_ASM void secure_fn_return(void);
 
 NONSECURE_ENTRY_ASM void secure_indirect_call(void);
 
+NONSECURE_ENTRY_ASM void PendSV_hook0(void);
+
 #ifdef __cplusplus
 }
 #endif 
@@ -0,0 +1,376 @@
+//! Rebuild the layout the secure runtime produced for an epoch
+//!
+//! The runtime draws a fresh seed for every epoch and the layout only depends
+//! on that seed, the metadata, the sandbox banks and the settings recorded with
+//! the seed. Given a record of `runtime::seed_log`, this tool runs the same
+//! randomizer on the host, prints
+//! the resulting `DISPATCH_TBL` and maps sandbox addresses (e.g. a faulting PC)
+//! back to the original function and offset.
+//!
+//! With `--pack`, it writes the metadata as a signed binary image instead, to be
+//! flashed alongside the non-secure firmware.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde::Deserialize;
+
+use secure_rt_core::adjustment::{Branch, RelocKind};
+use secure_rt_core::image;
+use secure_rt_core::{validate_metadata, Callsite, ChaChaDrbg, CodeProtection, Metadata, Object, ObjectKind, PaddingPolicy, Platform, Randomizer, Trap, NUM_OF_BANKS};
+
+const USAGE: &str = "\
+usage: harm-layout --seed <hex> --staging <n> --max-gap <bytes> [options] [address ...]
+       harm-layout --pack <file> --key <file> [options]
+
+The secure runtime prints the --seed, --staging, --max-gap and --execute-only
+arguments of every epoch, or keeps them in SEED_HISTORY.
+
+options:
+    --seed <hex>          64 hex digits, the seed of the epoch
+    --staging <n>         index of the sandbox bank the layout was built in
+    --max-gap <bytes>     largest random gap before an object, the max_gap of PADDING
+    --execute-only        the layout was built for CodeProtection::ExecuteOnly
+    --epoch <n>           epoch the seed belongs to, only labels the output (default 0)
+    --metadata <dir>      directory holding objects.yaml, callsites.yaml and sandbox.yaml (default metadata)
+    --bank <base>:<size>  sandbox bank, given once per bank (default the banks of sandbox.yaml)
+    --pack <file>         write the signed metadata image to <file> and its public key to <file>.pub
+    --key <file>          file holding the 64 hex digits of the Ed25519 signing key seed
+
+Every address is mapped back to the original function and offset.";
+
+#[derive(Deserialize)]
+struct RelocInfo {
+    src_offset: u16,
+    dst_index: u16,
+    dst_offset: u16,
+    #[serde(default)]
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+enum ObjectKindInfo {
+    Function,
+    BasicBlock,
+    VectorTable,
+    Data,
+}
+
+#[derive(Deserialize)]
+struct ObjectInfo {
+    name: String,
+    kind: ObjectKindInfo,
+    reloc_items: Vec<RelocInfo>,
+    address: u32,
+    size: u16,
+    isr: u16,
+}
+
+#[derive(Deserialize)]
+struct CallsiteInfo {
+    caller: u16,
+    offsets: Vec<u16>,
+}
+
+#[derive(Deserialize)]
+struct BankInfo {
+    base: usize,
+    size: usize,
+}
+
+#[derive(Deserialize)]
+struct SandboxInfo {
+    banks: Vec<BankInfo>,
+}
+
+/// Host memory standing in for the flash and the sandbox banks
+struct HostPlatform {
+    regions: Vec<(usize, Vec<u8>)>,
+}
+
+impl Platform for HostPlatform {
+    fn set_vtor(&mut self, _address: usize) {}
+
+    unsafe fn memory<'m>(&self, address: usize, length: usize) -> &'m mut [u8] {
+        for (base, memory) in self.regions.iter() {
+            if address >= *base && address + length <= base + memory.len() {
+                let ptr = memory.as_ptr().add(address - base) as *mut u8;
+                return std::slice::from_raw_parts_mut(ptr, length);
+            }
+        }
+        panic!("unmapped memory access at 0x{:x} ({} bytes)", address, length);
+    }
+
+    fn critical_section<R, F: FnOnce() -> R>(f: F) -> R {
+        f()
+    }
+}
+
+struct Options {
+    seed: Option<[u8; 32]>,
+    staging: Option<usize>,
+    max_gap: Option<usize>,
+    protection: CodeProtection,
+    epoch: usize,
+    metadata: PathBuf,
+    banks: Option<[(usize, usize); NUM_OF_BANKS]>,
+    addresses: Vec<usize>,
+    pack: Option<PathBuf>,
+    key: Option<PathBuf>,
+}
+
+fn parse_number(s: &str) -> Result<usize, String> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number `{}`", s))
+}
+
+fn parse_seed(s: &str) -> Result<[u8; 32], String> {
+    let mut seed = [0u8; 32];
+    if s.len() != 64 || !s.is_ascii() {
+        return Err(format!("the seed must be 64 hex digits, got `{}`", s));
+    }
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i .. 2 * i + 2], 16).map_err(|_| format!("invalid seed `{}`", s))?;
+    }
+    Ok(seed)
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = env::args().skip(1);
+    let mut seed = None;
+    let mut staging = None;
+    let mut max_gap = None;
+    let mut protection = CodeProtection::Readable;
+    let mut epoch = 0;
+    let mut metadata = PathBuf::from("metadata");
+    let mut banks = Vec::new();
+    let mut addresses = Vec::new();
+    let mut pack = None;
+    let mut key = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value of {}", arg));
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_seed(&value()?)?),
+            "--staging" => staging = Some(parse_number(&value()?)?),
+            "--max-gap" => max_gap = Some(parse_number(&value()?)?),
+            "--execute-only" => protection = CodeProtection::ExecuteOnly,
+            "--epoch" => epoch = parse_number(&value()?)?,
+            "--metadata" => metadata = PathBuf::from(value()?),
+            "--bank" => {
+                let bank = value()?;
+                let mut parts = bank.splitn(2, ':');
+                let base = parse_number(parts.next().unwrap())?;
+                let size = parse_number(parts.next().ok_or(format!("invalid bank `{}`", bank))?)?;
+                banks.push((base, size));
+            },
+            "--pack" => pack = Some(PathBuf::from(value()?)),
+            "--key" => key = Some(PathBuf::from(value()?)),
+            "-h" | "--help" => return Err(String::new()),
+            _ => addresses.push(parse_number(&arg)?),
+        }
+    }
+
+    let banks = match banks.len() {
+        0 => None,
+        NUM_OF_BANKS => Some([banks[0], banks[1]]),
+        n => return Err(format!("expected {} banks, got {}", NUM_OF_BANKS, n)),
+    };
+
+    if pack.is_none() && (seed.is_none() || staging.is_none() || max_gap.is_none()) {
+        return Err("--seed, --staging and --max-gap are required".into());
+    }
+    if matches!(staging, Some(staging) if staging >= NUM_OF_BANKS) {
+        return Err(format!("--staging must be below {}", NUM_OF_BANKS));
+    }
+    if pack.is_some() && key.is_none() {
+        return Err("--pack requires --key".into());
+    }
+
+    Ok(Options {
+        seed,
+        staging,
+        max_gap,
+        protection,
+        epoch,
+        metadata,
+        banks,
+        addresses,
+        pack,
+        key,
+    })
+}
+
+fn parse_reloc_kind(s: &str) -> Result<RelocKind, String> {
+    Ok(match s {
+        "B_T1" => RelocKind::B_T1,
+        "B_T2" => RelocKind::B_T2,
+        "B_T3" => RelocKind::B_T3,
+        "B_T4" => RelocKind::B_T4,
+        "BL" => RelocKind::BL,
+        "BLX" => RelocKind::BLX,
+        "CBZ" => RelocKind::CBZ,
+        "ADR_T1" => RelocKind::ADR_T1,
+        "ADR_W" => RelocKind::ADR_W,
+        "LDR_T1" => RelocKind::LDR_T1,
+        "LDR_W" => RelocKind::LDR_W,
+        "MOVW" => RelocKind::MOVW,
+        "MOVT" => RelocKind::MOVT,
+        "B_W" => RelocKind::B_W,
+        _ => return Err(format!("unknown relocation kind `{}`", s)),
+    })
+}
+
+/// Load the metadata emitted by `harm-rw`, the tables are leaked like the static ones of the runtime
+fn load_metadata(dir: &Path) -> Result<(Metadata<'static>, Vec<String>), String> {
+    let read = |name: &str| {
+        let path = dir.join(name);
+        fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e))
+    };
+    let infos: Vec<ObjectInfo> = serde_yaml::from_str(&read("objects.yaml")?).map_err(|e| e.to_string())?;
+    let callsite_infos: Vec<CallsiteInfo> = serde_yaml::from_str(&read("callsites.yaml")?).map_err(|e| e.to_string())?;
+
+    let mut objects = Vec::new();
+    let mut branches = Vec::new();
+
+    for (index, info) in infos.iter().enumerate() {
+        let reloc_items = if info.reloc_items.is_empty() {
+            None
+        } else {
+            Some((branches.len() as u16, (branches.len() + info.reloc_items.len()) as u16))
+        };
+        for item in info.reloc_items.iter() {
+            let kind = item.kind.as_deref().map_or(Ok(RelocKind::B_W), parse_reloc_kind)?;
+            branches.push(Branch { src_offset: item.src_offset, dst_object: item.dst_index, dst_offset: item.dst_offset, kind });
+        }
+
+        let object = Object { reloc_items, address: info.address as usize, size: info.size, index: index as u16 };
+        objects.push(match info.kind {
+            ObjectKindInfo::Function => ObjectKind::Function(object),
+            ObjectKindInfo::BasicBlock => ObjectKind::BasicBlock(object),
+            ObjectKindInfo::VectorTable => ObjectKind::VectorTable(object),
+            ObjectKindInfo::Data => ObjectKind::Data(object),
+        });
+    }
+
+    let objects: &'static [ObjectKind] = Box::leak(objects.into_boxed_slice());
+    let mut vectors: Vec<(&'static ObjectKind, u16)> = infos.iter().enumerate()
+        .filter(|(_, info)| info.isr != 0)
+        .map(|(index, info)| (&objects[index], info.isr))
+        .collect();
+    vectors.sort_by_key(|v| v.1);
+
+    let callsites: Vec<Callsite> = callsite_infos.iter()
+        .flat_map(|cs| cs.offsets.iter().map(move |&offset| Callsite { offset, caller: cs.caller }))
+        .collect();
+
+    let metadata = Metadata {
+        objects,
+        branches: Box::leak(branches.into_boxed_slice()),
+        vectors: Box::leak(vectors.into_boxed_slice()),
+        callsites: Box::leak(callsites.into_boxed_slice()),
+    };
+    Ok((metadata, infos.into_iter().map(|info| info.name).collect()))
+}
+
+/// Load the sandbox banks the runtime was built with from `sandbox.yaml`
+fn load_banks(dir: &Path) -> Result<[(usize, usize); NUM_OF_BANKS], String> {
+    let path = dir.join("sandbox.yaml");
+    let sandbox = fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let sandbox: SandboxInfo = serde_yaml::from_str(&sandbox).map_err(|e| e.to_string())?;
+
+    match sandbox.banks.as_slice() {
+        [first, second] => Ok([(first.base, first.size), (second.base, second.size)]),
+        banks => Err(format!("expected {} banks in {}, got {}", NUM_OF_BANKS, path.display(), banks.len())),
+    }
+}
+
+/// Write `metadata` as an image signed with the key seed in `key`, followed by the public key in `<output>.pub`
+fn pack(metadata: &Metadata, output: &Path, key: &Path) -> Result<(), String> {
+    let key_seed = fs::read_to_string(key).map_err(|e| format!("cannot read {}: {}", key.display(), e))?;
+    let key_seed = parse_seed(key_seed.trim()).map_err(|_| format!("{} must hold 64 hex digits", key.display()))?;
+
+    let mut signed = image::serialize(metadata);
+    let signature = image::sign(&signed, &key_seed);
+    signed.extend_from_slice(&signature);
+
+    let public_key = image::public_key(&key_seed);
+    let key_output = PathBuf::from(format!("{}.pub", output.display()));
+    fs::write(output, &signed).map_err(|e| format!("cannot write {}: {}", output.display(), e))?;
+    fs::write(&key_output, public_key).map_err(|e| format!("cannot write {}: {}", key_output.display(), e))?;
+
+    println!("Wrote {} bytes of signed metadata to {}", signed.len(), output.display());
+    println!("Public key in {}", key_output.display());
+    Ok(())
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let (metadata, names) = load_metadata(&options.metadata)?;
+    let banks = match options.banks {
+        Some(banks) => banks,
+        None => load_banks(&options.metadata)?,
+    };
+    if let Err(violations) = validate_metadata(&metadata, &banks) {
+        let violations: Vec<String> = violations.iter().map(|e| format!("  {}", e)).collect();
+        return Err(format!("invalid metadata:\n{}", violations.join("\n")));
+    }
+
+    if let (Some(output), Some(key)) = (&options.pack, &options.key) {
+        return pack(&metadata, output, key);
+    }
+
+    // the code itself does not matter, map enough zeroed memory for the flash and the banks
+    let flash_base = metadata.objects.iter().map(|o| o.get_object().get_address()).min().unwrap_or(0);
+    let flash_end = metadata.objects.iter().map(|o| o.get_object().get_address() + o.get_object().get_size()).max().unwrap_or(0);
+    let mut regions = vec![(flash_base, vec![0u8; flash_end - flash_base])];
+    regions.extend(banks.iter().map(|&(base, size)| (base, vec![0u8; size])));
+
+    // a fresh randomizer builds its first layout in the first bank
+    let mut banks = banks;
+    banks.rotate_left(options.staging.unwrap());
+
+    let dispatch_tbl: &'static mut [u32] = Box::leak(vec![0u32; metadata.objects.len()].into_boxed_slice());
+    let rng = ChaChaDrbg::from_seed(options.seed.unwrap());
+    let mut randomizer = unsafe { Randomizer::new(HostPlatform { regions }, rng, metadata, banks, dispatch_tbl) };
+    // the trap does not change the layout
+    randomizer.set_padding(PaddingPolicy { max_gap: options.max_gap.unwrap(), trap: Trap::Udf });
+    randomizer.set_protection(options.protection).map_err(|e| format!("cannot protect the code: {}", e))?;
+    randomizer.randomize().map_err(|e| format!("cannot randomize: {}", e))?;
+
+    println!("Layout entropy: {}", randomizer.entropy());
+    println!("DISPATCH_TBL of epoch {}:", options.epoch);
+    for (i, object) in metadata.objects.iter().enumerate() {
+        let obj = object.get_object();
+        println!("{:5} 0x{:08x} 0x{:08x} {:6} {}", i, randomizer.get_instance_address(i), obj.get_address(), obj.get_size(), names[i]);
+    }
+
+    for &address in options.addresses.iter() {
+        match randomizer.lookup(address & !1) {
+            Some((object, offset)) => {
+                let obj = object.get_object();
+                println!("0x{:08x} = {}+0x{:x} (originally 0x{:08x})",
+                         address, names[obj.index as usize], offset, obj.get_address() + offset);
+            },
+            None => println!("0x{:08x} is not in the sandbox", address),
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let result = parse_args().and_then(run);
+
+    if let Err(message) = result {
+        if !message.is_empty() {
+            eprintln!("error: {}", message);
+        }
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
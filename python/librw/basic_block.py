@@ -0,0 +1,194 @@
+"""
+Split functions into basic blocks that the secure runtime places independently.
+
+The entry block of a function keeps the index of the function, so function
+pointers, vectors and the dispatch table keep working. The other blocks are
+appended after all functions as "BasicBlock" objects. A block whose last
+instruction continues into the next block gets a fall-through edge, emitted as
+its last reloc item with a source offset equal to the block size; the runtime
+appends a B.W there when the block is placed.
+
+A block boundary is only placed where no PC-relative reference other than a
+wide B/B<c> crosses it (literal loads, ADR, CBZ/CBNZ, IT blocks and narrow
+branches stay within one block). Functions using jump tables are not split.
+"""
+
+from capstone.arm import ARM_CC_AL
+
+from librw.ir.block import BlockIR
+from librw.ir.branch import BranchIR
+from librw.ir.function import FunctionIR
+from librw.ir.indirect_branch import IndirectBranchIR
+from librw.ir.load_branch_address import LoadBranchAddressIR
+from librw.ir.ref import RefIR
+from librw.ir.table_branch import BranchTableIR, LoadBranchTableIR, TableBranchEntryIR, TableBranchIR
+
+
+def _leaves(ir):
+    if isinstance(ir, BlockIR):
+        for i in ir.child_iter():
+            yield from _leaves(i)
+    else:
+        yield ir
+
+
+def _function_of(ir):
+    while ir is not None and not isinstance(ir, FunctionIR):
+        ir = ir.parent
+    return ir
+
+
+def _is_jump(ir, fn):
+    """ Top-level wide B/B<c> to another instruction of fn, the only reference a block boundary may cut """
+    return isinstance(ir, BranchIR) and not ir.link and ir.parent is fn and \
+        ir.ref is not None and ir.ref.parent is fn and not isinstance(ir.ref, FunctionIR)
+
+
+def _is_terminator(ir):
+    """ Control never continues to the next instruction """
+    if isinstance(ir, BranchIR):
+        return not ir.link and ir.cond == ARM_CC_AL
+    if isinstance(ir, IndirectBranchIR):
+        return not ir.link
+    return False
+
+
+def widen_branches(fw):
+    """ Use the wide encoding for intra-function branches, so that their blocks may be placed far apart """
+    for fn in fw.child_iter():
+        if isinstance(fn, FunctionIR):
+            for ir in fn.child_iter():
+                if _is_jump(ir, fn) and ir.len == 2:
+                    ir.len = 4
+
+
+def _unsplittable(fw):
+    """ Functions using jump tables or computed branches """
+    pinned = set()
+    for fn in fw.child_iter():
+        if isinstance(fn, BranchTableIR):
+            for entry in _leaves(fn):
+                if isinstance(entry, TableBranchEntryIR) and entry.ref is not None:
+                    pinned.add(_function_of(entry.ref))
+        elif isinstance(fn, FunctionIR):
+            for ir in _leaves(fn):
+                if isinstance(ir, (LoadBranchTableIR, TableBranchIR, LoadBranchAddressIR, TableBranchEntryIR)):
+                    pinned.add(fn)
+    return pinned
+
+
+def split_function(fn):
+    """ Offsets where the blocks of fn start, the first one is always 0 """
+    starts = {0}
+    for ir in fn.child_iter():
+        if _is_jump(ir, fn):
+            starts.add(ir.ref.offset)
+        if _is_terminator(ir):
+            starts.add(ir.offset + ir.len)
+
+    # PC-relative references that must not be cut
+    spans = []
+    for ir in _leaves(fn):
+        if isinstance(ir, RefIR) and ir.ref is not None and not _is_jump(ir, fn) and \
+                fn.addr <= ir.ref.addr < fn.addr + fn.len:
+            spans.append((min(ir.addr, ir.ref.addr) - fn.addr, max(ir.addr, ir.ref.addr) - fn.addr))
+
+    boundaries = set(i.offset for i in fn.child_iter())
+    return sorted(b for b in starts if b == 0 or (b in boundaries and b < fn.len and
+                                                 not any(lo < b <= hi for lo, hi in spans)))
+
+
+class BlockMap:
+    """ Basic blocks of all functions and their object indices """
+
+    def __init__(self, fw, objects):
+        self.objects = objects
+        self.blocks = dict()
+        self.extra = list()
+        pinned = _unsplittable(fw)
+
+        for fn in objects:
+            if not isinstance(fn, FunctionIR):
+                continue
+            starts = [0] if fn in pinned else split_function(fn)
+            ends = starts[1:] + [fn.len]
+            blocks = []
+            for n, (start, end) in enumerate(zip(starts, ends)):
+                if n == 0:
+                    index = objects.index(fn)
+                else:
+                    index = len(objects) + len(self.extra)
+                    self.extra.append((fn, n, start, end))
+                blocks.append((index, start, end))
+            self.blocks[fn] = blocks
+
+    @property
+    def count(self):
+        return len(self.objects) + len(self.extra)
+
+    def locate(self, fn, offset):
+        """ Index of the block of fn holding offset and the offset within that block """
+        for index, start, end in self.blocks[fn]:
+            if start <= offset < end:
+                return index, offset - start
+        index, start, _ = self.blocks[fn][-1]
+        return index, offset - start
+
+    def _reloc_items(self, fn, start, end):
+        items = []
+        for ir in _leaves(fn):
+            if not isinstance(ir, BranchIR) or ir.ref is None or not start <= ir.addr - fn.addr < end:
+                continue
+            src_offset = ir.addr - fn.addr - start
+            ref = ir.ref
+            if isinstance(ref, FunctionIR):
+                items.append({"src_offset": src_offset, "dst_index": self.objects.index(ref), "dst_offset": 0,
+                              "kind": ir.reloc_kind})
+                continue
+            dst_fn = _function_of(ref)
+            if dst_fn is None or dst_fn not in self.blocks:
+                continue
+            dst_index, dst_offset = self.locate(dst_fn, ref.addr - dst_fn.addr)
+            if dst_fn is fn and start <= ref.addr - fn.addr < end:
+                # stays within the block, the PC-relative offset is still valid
+                continue
+            items.append({"src_offset": src_offset, "dst_index": dst_index, "dst_offset": dst_offset,
+                          "kind": ir.reloc_kind})
+        return items
+
+    def _last_child(self, fn, end):
+        last = None
+        for ir in fn.child_iter():
+            if ir.offset < end:
+                last = ir
+        return last
+
+    def object_yaml(self, fn, n):
+        """ Description of the n-th block of fn """
+        index, start, end = self.blocks[fn][n]
+        desp = fn.output_yaml_desp(index)
+        desp["address"] = fn.addr + start
+        desp["size"] = end - start
+        desp["reloc_items"] = self._reloc_items(fn, start, end)
+        if n > 0:
+            desp["kind"] = "BasicBlock"
+            desp["name"] = "%s.bb%d" % (fn.name, n)
+        elif hasattr(fn, "irq"):
+            desp["isr"] = fn.irq
+
+        # continue into the next block
+        if n + 1 < len(self.blocks[fn]) and not _is_terminator(self._last_child(fn, end)):
+            desp["reloc_items"].append({"src_offset": end - start, "dst_index": self.blocks[fn][n + 1][0],
+                                        "dst_offset": 0, "kind": "B_T4"})
+        return desp
+
+    def output_yaml(self):
+        output = []
+        for obj in self.objects:
+            if isinstance(obj, FunctionIR):
+                output.append(self.object_yaml(obj, 0))
+            else:
+                output.append(obj.output_yaml_desp(self.objects.index(obj)))
+        for fn, n, _, _ in self.extra:
+            output.append(self.object_yaml(fn, n))
+        return output
@@ -43,6 +43,15 @@ class BranchIR(RefIR):
         if self.__link:
             self.len = 4
 
+    @property
+    def reloc_kind(self):
+        """ Encoding of the branch, as named by RelocKind of the secure runtime """
+        if self.link:
+            return "BL"
+        if self.cond == ARM_CC_AL or isinstance(self.parent, ITBlockIR):
+            return "B_T2" if self.len == 2 else "B_T4"
+        return "B_T1" if self.len == 2 else "B_T3"
+
     def __calc_disp(self):
         return abs(self.ref.addr - self.addr - 4)
 
@@ -17,6 +17,7 @@ from librw.ir.object import ObjectIR
 from librw.ir.ret_encode import LoadFuncPtrIR, LoadReturnIndexIR
 from librw.ir.table_branch import BranchTableIR
 from librw.ir.vector import VectorIR
+from librw.basic_block import BlockMap, widen_branches
 from librw.rw import fw_instrument
 from librw.symbol import Symbol
 
@@ -175,12 +176,14 @@ def output_revise_yaml(ir, fn, objects, output):
                     "src_offset": ir.addr - fn.addr,
                     "dst_index": objects.index(ref),
                     "dst_offset": 0,
+                    "kind": ir.reloc_kind,
                 }
             elif ref.parent is not fn:
                 item = {
                     "src_offset": ir.addr - fn.addr,
                     "dst_index": objects.index(ref.parent),
-                    "dst_offset": ref.addr - ref.parent.addr
+                    "dst_offset": ref.addr - ref.parent.addr,
+                    "kind": ir.reloc_kind,
                 }
             else:
                 pass
@@ -200,22 +203,33 @@ def output_object_yaml(objects):
     return output
 
 
-def output_objects_yaml(fw, path):
+def output_objects_yaml(fw, path, basic_blocks=False):
     objects = list(filter(lambda x: isinstance(x, FunctionIR) or isinstance(x, VectorIR),
                             [o for o in fw.child_iter()]))
+    block_map = BlockMap(fw, objects) if basic_blocks else None
+
     with open(path + "/objects.yaml", 'w') as f:
-        output = output_object_yaml(objects)
+        if block_map:
+            output = block_map.output_yaml()
+            print("Total %d basic blocks" % block_map.count)
+        else:
+            output = output_object_yaml(objects)
         f.write(yaml.dump(output, allow_unicode=True))
 
+    # callsites are listed in the order of their ids, a return site belongs to the block holding it
     with open(path + "/callsites.yaml", "w") as f:
         output = []
         for fn in objects[1:]:
-            callsite = { "caller": objects.index(fn), "offsets": [] }
             for ir in fn.child_iter():
                 if isinstance(ir, LoadReturnIndexIR):
-                    callsite["offsets"].append(ir.ret_offset)
-            if callsite["offsets"]:
-                output.append(callsite)
+                    if block_map:
+                        caller, offset = block_map.locate(fn, ir.ret_offset)
+                    else:
+                        caller, offset = objects.index(fn), ir.ret_offset
+                    if output and output[-1]["caller"] == caller:
+                        output[-1]["offsets"].append(offset)
+                    else:
+                        output.append({ "caller": caller, "offsets": [offset] })
         f.write(yaml.dump(output, allow_unicode=True))
 
 
@@ -318,7 +332,8 @@ def main(argv):
     output_path = argv.metadata_path
     entry_point = int(argv.entry_point, base=16)
     cmse_lib = argv.cmse_lib
-    has_rtos = False
+    has_rtos = argv.rtos
+    basic_blocks = argv.basic_blocks
     do_inst = True
 
     # try:
@@ -383,6 +398,9 @@ def main(argv):
         if do_inst:
             report = fw_instrument(fw, cmse_fn, has_rtos)
 
+        if basic_blocks:
+            widen_branches(fw)
+
         fw.layout_refresh()
 
         if do_inst:
@@ -411,7 +429,7 @@ def main(argv):
 
         print("New firmware length: %d (%d) bytes" % (fw.len, len(fw.code)))
         # output_c_syntax(fw, output_path)
-        output_objects_yaml(fw, output_path)
+        output_objects_yaml(fw, output_path, basic_blocks)
         print("Function pointer table size: %d" % func_ptr_tbl_sz)
         print("Total function size (original): %d" % orig_fn_total_size)
         print("Total function size (new): %d" % new_fn_total_size)
@@ -428,6 +446,9 @@ if __name__ == '__main__':
     argp.add_argument('-i', '--input-file', type=str, dest='input_file', help='Target firmware (in ELF format)')
     argp.add_argument('-o', '--output-file', type=str, dest='output_file', help='Output name of instrumented target firmware')
     argp.add_argument('-p', '--metadata-output-path', dest='metadata_path', type=str, help='Output path of metadata files')
+    argp.add_argument('-r', '--rtos', dest='rtos', action='store_true', help='Hook PendSV for runtime re-randomization')
+    argp.add_argument('-b', '--basic-blocks', dest='basic_blocks', action='store_true',
+                      help='Randomize at basic block granularity')
 
     argv = argp.parse_args()
 
@@ -0,0 +1,351 @@
+/// Reloc item, an instruction referring to `dst_offset` bytes into object `dst_object`
+///
+/// A `src_offset` equal to the size of the object denotes a fall-through edge,
+/// see `Metadata::get_fall_through()`. The `dst_offset` of an absolute
+/// reference (`MOVW`/`MOVT`) to a function is odd, to carry the Thumb bit.
+/// The layout is the branch record of a binary metadata image, see `image`.
+#[repr(C)]
+pub struct Branch {
+    /// offset of the instruction in its own object
+    pub src_offset: u16,
+    /// index of the object referred to, may be the object holding the instruction
+    pub dst_object: u16,
+    /// offset of the target in `dst_object`, not 0 for branches into the middle of a function
+    pub dst_offset: u16,
+    pub kind: RelocKind,
+}
+
+impl Branch {
+    /// Whether the instruction refers to its own object without depending on where the object is placed
+    ///
+    /// Copying the object keeps such references valid, so they are left alone.
+    #[inline]
+    pub fn is_position_independent(&self, object: u16, object_size: usize) -> bool {
+        self.dst_object == object && self.kind.is_pc_relative() && (self.src_offset as usize) < object_size
+    }
+}
+
+/// Thumb-2 instructions referring to another object
+///
+/// Names follow the encodings of the ARMv7-M/ARMv8-M architecture reference manual.
+/// The discriminants are the codes of a binary metadata image.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(u8)]
+pub enum RelocKind {
+    /// `B<c>` narrow (T1)
+    B_T1 = 1,
+    /// `B` narrow (T2)
+    B_T2,
+    /// `B<c>.W` (T3)
+    B_T3,
+    /// `B.W` (T4)
+    B_T4,
+    /// `BL`
+    BL,
+    /// `BLX` immediate, switches to the ARM state so M-profile cores never execute it
+    BLX,
+    /// `CBZ` or `CBNZ`, forward only
+    CBZ,
+    /// `ADR` narrow (T1), forward only
+    ADR_T1,
+    /// `ADR.W` (T2 or T3, picked by the direction of the target)
+    ADR_W,
+    /// `LDR` literal narrow (T1), forward only
+    LDR_T1,
+    /// `LDR<size>.W` literal, picks the direction of the offset
+    LDR_W,
+    /// `MOVW` of the low half of the target address
+    MOVW,
+    /// `MOVT` of the high half of the target address
+    MOVT,
+    /// Any of `B<c>.W`, `B.W`, `BL` and `BLX`, told apart by the instruction being relocated
+    ///
+    /// Metadata written before the relocation kinds were recorded only holds these.
+    B_W = 0,
+}
+
+impl RelocKind {
+    /// Length of the instruction in bytes
+    #[inline]
+    pub fn size(self) -> usize {
+        match self {
+            RelocKind::B_T1 | RelocKind::B_T2 | RelocKind::CBZ | RelocKind::ADR_T1 | RelocKind::LDR_T1 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Whether the target is relative to the instruction rather than absolute
+    #[inline]
+    pub fn is_pc_relative(self) -> bool {
+        !matches!(self, RelocKind::MOVW | RelocKind::MOVT)
+    }
+
+    /// Whether the instruction only transfers control, so that it may go through a trampoline
+    #[inline]
+    pub fn is_branch(self) -> bool {
+        matches!(self, RelocKind::B_T1 | RelocKind::B_T2 | RelocKind::B_T3 | RelocKind::B_T4 | RelocKind::BL | RelocKind::CBZ)
+    }
+
+    /// Whether the instruction takes the address of its target or loads from it, the only references to data
+    #[inline]
+    pub fn is_data_reference(self) -> bool {
+        matches!(self, RelocKind::ADR_T1 | RelocKind::ADR_W | RelocKind::LDR_T1 | RelocKind::LDR_W | RelocKind::MOVW | RelocKind::MOVT)
+    }
+
+    /// The actual kind of `src_code`, which only differs from `self` for `B_W`
+    #[inline]
+    pub fn resolve(self, src_code: u32) -> RelocKind {
+        match self {
+            RelocKind::B_W => detect_wide_branch(src_code),
+            kind => kind,
+        }
+    }
+
+    /// Whether the encoding may not hold the offset of a target up to `distance` bytes away
+    pub fn may_miss(self, distance: usize) -> bool {
+        match self.reach() {
+            Some((min, max, _)) => (min.unsigned_abs() as usize) < distance || (max as usize) < distance,
+            None => false,
+        }
+    }
+
+    /// Offsets `(min, max)` from the PC the encoding holds, along with the alignment they need
+    fn reach(self) -> Option<(i32, i32, i32)> {
+        match self {
+            RelocKind::B_T1 => Some((-256, 254, 2)),
+            RelocKind::B_T2 => Some((-2048, 2046, 2)),
+            RelocKind::B_T3 => Some((-(1 << 20), (1 << 20) - 2, 2)),
+            RelocKind::B_T4 | RelocKind::BL => Some((-(1 << 24), (1 << 24) - 2, 2)),
+            RelocKind::BLX => Some((-(1 << 24), (1 << 24) - 4, 4)),
+            RelocKind::CBZ => Some((0, 126, 2)),
+            RelocKind::ADR_T1 | RelocKind::LDR_T1 => Some((0, 1020, 4)),
+            RelocKind::ADR_W | RelocKind::LDR_W => Some((-4095, 4095, 1)),
+            RelocKind::MOVW | RelocKind::MOVT | RelocKind::B_W => None,
+        }
+    }
+
+    /// Offset of `dst_addr` from the PC (aligned for literal accesses) of an instruction at `src_addr`
+    fn offset(self, src_addr: usize, dst_addr: usize) -> i32 {
+        match self {
+            RelocKind::BLX | RelocKind::ADR_T1 | RelocKind::ADR_W | RelocKind::LDR_T1 | RelocKind::LDR_W => {
+                dst_addr as i32 - aligned_pc(src_addr)
+            },
+            _ => dst_addr as i32 - pc(src_addr),
+        }
+    }
+}
+
+/// Why an instruction cannot be encoded to refer to a target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RelocError {
+    /// The target is further than the encoding reaches
+    OutOfRange {
+        kind: RelocKind,
+        offset: i32,
+    },
+    /// The target is not aligned as the encoding requires
+    Misaligned {
+        kind: RelocKind,
+        offset: i32,
+    },
+}
+
+/// Long-branch trampoline `LDR.W PC, [PC, #0]`, followed by the target address
+pub const TRAMPOLINE_SIZE: usize = 8;
+
+/// `LDR.W PC, [PC, #0]`, the first word of a trampoline
+pub const TRAMPOLINE_LDR: u32 = 0xf000_f8df;
+
+/// Words of a trampoline to `dst_addr`
+///
+/// The trampoline itself must be placed at a word-aligned address, since its
+/// `LDR` reads the literal from the aligned PC; `dst_addr` may be any address.
+pub fn encode_trampoline(dst_addr: usize) -> [u32; 2] {
+    [TRAMPOLINE_LDR, dst_addr as u32 | 1]
+}
+
+// Instructions are handled the way they are laid out in memory: a 32-bit
+// instruction read as a little-endian word holds its first halfword in the low
+// half, unlike the manual, which writes the first halfword on the left.
+
+#[inline]
+pub(crate) fn halfwords(code: u32) -> (u32, u32) {
+    (code & 0xffff, code >> 16)
+}
+
+#[inline]
+fn word(hw1: u32, hw2: u32) -> u32 {
+    (hw1 & 0xffff) | (hw2 << 16)
+}
+
+/// Value of the PC seen by an instruction at `src_addr`
+#[inline]
+pub(crate) fn pc(src_addr: usize) -> i32 {
+    src_addr as i32 + 4
+}
+
+/// `Align(PC, 4)`, the base of `ADR`, `LDR` literal and `BLX` immediate
+#[inline]
+pub(crate) fn aligned_pc(src_addr: usize) -> i32 {
+    pc(src_addr) & !3
+}
+
+/// Rewrite Thumb-2 branch instructions, `decoder` reads them back
+///
+/// See: http://class.ece.iastate.edu/cpre288/resources/docs/Thumb-2SupplementReferenceManual.pdf (latest access: 4/26/2022)
+///
+#[allow(non_snake_case)]
+fn encode_B_T1(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let offset = dst_addr as i32 - pc(src_addr);
+    let imm8 = ((offset >> 1) & 0xff) as u32;
+
+    (src_code & !0xff) | imm8
+}
+
+#[allow(non_snake_case)]
+fn encode_B_T2(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let offset = dst_addr as i32 - pc(src_addr);
+    let imm11 = ((offset >> 1) & 0x7ff) as u32;
+
+    (src_code & !0x7ff) | imm11
+}
+
+#[allow(non_snake_case)]
+fn encode_B_T3(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let offset = dst_addr as i32 - pc(src_addr);
+    let (hw1, hw2) = halfwords(src_code);
+    let s: u32 = if offset < 0 { 1 } else { 0 };
+    let j1: u32 = if offset & (1 << 18) != 0 { 1 } else { 0 };
+    let j2: u32 = if offset & (1 << 19) != 0 { 1 } else { 0 };
+    let imm6: u32 = ((offset >> 12) & 0b111111) as u32;
+    let imm11: u32 = ((offset >> 1) & 0b11111111111) as u32;
+
+    // keep the condition
+    word((hw1 & 0b1111_1011_1100_0000) | (s << 10) | imm6,
+         (hw2 & 0b1101_0000_0000_0000) | (j1 << 13) | (j2 << 11) | imm11)
+}
+
+/// Offset fields `S:I1:I2:imm10` and `imm11` shared by `B.W`, `BL` and `BLX` into `(hw1, hw2)`
+fn encode_long_offset(hw1: u32, hw2: u32, offset: i32) -> u32 {
+    let s: u32 = if offset < 0 { 1 } else { 0 };
+    let i1: u32 = if offset & (1 << 23) > 0 { 1 } else { 0 };
+    let i2: u32 = if offset & (1 << 22) > 0 { 1 } else { 0 };
+    let imm10: u32 = ((offset >> 12) & 0b1111111111) as u32;
+    let imm11: u32 = ((offset >> 1) & 0b11111111111) as u32;
+    let j1: u32 = ((!i1 & 1u32) ^ s) & 1u32;
+    let j2: u32 = ((!i2 & 1u32) ^ s) & 1u32;
+
+    word((hw1 & 0b1111_1000_0000_0000) | (s << 10) | imm10,
+         (hw2 & 0b1101_0000_0000_0000) | (j1 << 13) | (j2 << 11) | imm11)
+}
+
+#[allow(non_snake_case)]
+fn encode_B_T4(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let (hw1, hw2) = halfwords(src_code);
+    encode_long_offset(hw1, hw2, dst_addr as i32 - pc(src_addr))
+}
+
+#[allow(non_snake_case)]
+fn encode_BLX(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let (hw1, hw2) = halfwords(src_code);
+    // the target is word-aligned, imm10L:H leaves H clear
+    encode_long_offset(hw1, hw2, dst_addr as i32 - aligned_pc(src_addr)) & !(1 << 16)
+}
+
+#[allow(non_snake_case)]
+fn encode_CBZ(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let offset = (dst_addr as i32 - pc(src_addr)) as u32;
+    let i = (offset >> 6) & 1;
+    let imm5 = (offset >> 1) & 0b11111;
+
+    (src_code & !0b0000_0010_1111_1000) | (i << 9) | (imm5 << 3)
+}
+
+/// Narrow `ADR` and `LDR` literal share the `Rd:imm8` layout
+fn encode_imm8_literal(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let offset = (dst_addr as i32 - aligned_pc(src_addr)) as u32;
+    (src_code & !0xff) | ((offset >> 2) & 0xff)
+}
+
+#[allow(non_snake_case)]
+fn encode_ADR_W(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let offset = dst_addr as i32 - aligned_pc(src_addr);
+    let (hw1, hw2) = halfwords(src_code);
+    let imm12 = offset.unsigned_abs();
+    // T2 subtracts, T3 adds
+    let op: u32 = if offset < 0 { 0b1010_1111 } else { 0b0000_1111 };
+
+    word((hw1 & 0b1111_1000_0000_0000) | ((imm12 >> 11) & 1) << 10 | 0b10_0000_0000 | op,
+         (hw2 & 0x0f00) | ((imm12 >> 8) & 0b111) << 12 | (imm12 & 0xff))
+}
+
+#[allow(non_snake_case)]
+fn encode_LDR_W(src_code: u32, src_addr: usize, dst_addr: usize) -> u32 {
+    let offset = dst_addr as i32 - aligned_pc(src_addr);
+    let (hw1, hw2) = halfwords(src_code);
+    let u: u32 = if offset < 0 { 0 } else { 1 };
+
+    word((hw1 & !(1 << 7)) | (u << 7), (hw2 & 0xf000) | (offset.unsigned_abs() & 0xfff))
+}
+
+/// `MOVW` and `MOVT` share the `imm4:i:imm3:imm8` layout
+fn encode_imm16(src_code: u32, imm16: u32) -> u32 {
+    let (hw1, hw2) = halfwords(src_code);
+
+    word((hw1 & 0b1111_1011_1111_0000) | ((imm16 >> 11) & 1) << 10 | (imm16 >> 12) & 0xf,
+         (hw2 & 0x0f00) | ((imm16 >> 8) & 0b111) << 12 | (imm16 & 0xff))
+}
+
+/// Tell `B<c>.W`, `B.W`, `BL` and `BLX` apart
+pub(crate) fn detect_wide_branch(src_code: u32) -> RelocKind {
+    let (_, hw2) = halfwords(src_code);
+
+    match (hw2 >> 12) & 0b101 {
+        0b000 => RelocKind::B_T3,
+        0b001 => RelocKind::B_T4,
+        0b100 => RelocKind::BLX,
+        _ => RelocKind::BL,
+    }
+}
+
+/// `B.W` to itself, appended to a basic block falling through and relocated like any other branch
+pub const FALL_THROUGH: u32 = 0xbffe_f7ff;
+
+/// Unconditional `B.W` from `src_addr` to `dst_addr`, appended to a basic block falling through
+pub fn encode_fall_through(src_addr: usize, dst_addr: usize) -> Result<u32, RelocError> {
+    encode(RelocKind::B_T4, FALL_THROUGH, src_addr, dst_addr)
+}
+
+/// Re-encode `src_code`, an instruction of `kind` at `src_addr`, to refer to `dst_addr`
+///
+/// Narrow instructions live in the low halfword, the high one is returned unchanged.
+/// Fails rather than truncating when `dst_addr` is out of the reach of the encoding.
+pub fn encode(kind: RelocKind, src_code: u32, src_addr: usize, dst_addr: usize) -> Result<u32, RelocError> {
+    let kind = kind.resolve(src_code);
+
+    if let Some((min, max, align)) = kind.reach() {
+        let offset = kind.offset(src_addr, dst_addr);
+        if offset < min || offset > max {
+            return Err(RelocError::OutOfRange { kind, offset });
+        }
+        if offset % align != 0 {
+            return Err(RelocError::Misaligned { kind, offset });
+        }
+    }
+
+    Ok(match kind {
+        RelocKind::B_T1 => encode_B_T1(src_code, src_addr, dst_addr),
+        RelocKind::B_T2 => encode_B_T2(src_code, src_addr, dst_addr),
+        RelocKind::B_T3 => encode_B_T3(src_code, src_addr, dst_addr),
+        RelocKind::B_T4 | RelocKind::BL => encode_B_T4(src_code, src_addr, dst_addr),
+        RelocKind::BLX => encode_BLX(src_code, src_addr, dst_addr),
+        RelocKind::CBZ => encode_CBZ(src_code, src_addr, dst_addr),
+        RelocKind::ADR_T1 | RelocKind::LDR_T1 => encode_imm8_literal(src_code, src_addr, dst_addr),
+        RelocKind::ADR_W => encode_ADR_W(src_code, src_addr, dst_addr),
+        RelocKind::LDR_W => encode_LDR_W(src_code, src_addr, dst_addr),
+        RelocKind::MOVW => encode_imm16(src_code, dst_addr as u32 & 0xffff),
+        RelocKind::MOVT => encode_imm16(src_code, dst_addr as u32 >> 16),
+        RelocKind::B_W => unreachable!(),
+    })
+}
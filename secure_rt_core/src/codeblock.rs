@@ -1,27 +1,30 @@
 use core::mem::size_of_val;
-use core::slice::from_raw_parts_mut;
+
+use super::error::HarmError;
 
 #[repr(C)]
 pub struct CodeBlock<'a> {
+    /// address of the block as seen by the target
+    pub address: usize,
     pub block: &'a mut[u8],
 }
 
 impl<'a> CodeBlock<'a> {
-    pub unsafe fn from(address: usize, length: usize) -> CodeBlock<'a> {
+    pub fn new(address: usize, block: &'a mut [u8]) -> CodeBlock<'a> {
         CodeBlock {
-            block: from_raw_parts_mut(address as *mut u8, length),
+            address,
+            block,
         }
     }
 
     #[inline]
     fn get_available_space(&self, offset: usize) -> usize {
-        let length = size_of_val(self.block);
-        
-        if offset < length {
-            length - offset
-        } else {
-            0
-        }
+        size_of_val(self.block).saturating_sub(offset)
+    }
+
+    #[inline]
+    fn out_of_bounds(&self, offset: usize, length: usize) -> HarmError {
+        HarmError::OutOfBounds { address: self.address + offset, length }
     }
 
     pub fn size(&self) -> usize {
@@ -29,81 +32,81 @@ impl<'a> CodeBlock<'a> {
     }
 
     #[inline]
-    pub fn get_address(&self, offset: usize) -> Result<usize, ()> {
+    pub fn get_address(&self, offset: usize) -> Result<usize, HarmError> {
         if offset < size_of_val(self.block) {
-            Ok(&self.block[offset] as *const u8 as *const () as usize)
+            Ok(self.address + offset)
         } else {
-            Err(())
+            Err(self.out_of_bounds(offset, 1))
         }
     }
 
     #[inline]
-    pub fn read8(&self, offset: usize) -> Result<u8, ()> {
+    pub fn read8(&self, offset: usize) -> Result<u8, HarmError> {
         if self.get_available_space(offset) >= 1 {
             Ok(self.block[offset])
         } else {
-            Err(())
+            Err(self.out_of_bounds(offset, 1))
         }
     }
 
     #[inline]
-    pub fn read16(&self, offset: usize) -> Result<u16, ()> {
+    pub fn read16(&self, offset: usize) -> Result<u16, HarmError> {
         if self.get_available_space(offset) >= 2 {
             Ok(unsafe { *(&self.block[offset] as *const u8 as *const u16) })
         } else {
-            Err(())
+            Err(self.out_of_bounds(offset, 2))
         }
     }
 
     #[inline]
-    pub fn read32(&self, offset: usize) -> Result<u32, ()> {
+    pub fn read32(&self, offset: usize) -> Result<u32, HarmError> {
         if self.get_available_space(offset) >= 4 {
             Ok(unsafe { *(&self.block[offset] as *const u8 as *const u32) })
         } else {
-            Err(())
+            Err(self.out_of_bounds(offset, 4))
         }
     }
 
     #[inline]
-    pub fn write8(&mut self, offset: usize, value: u8) -> Result<u8, ()> {
+    pub fn write8(&mut self, offset: usize, value: u8) -> Result<u8, HarmError> {
         if self.get_available_space(offset) >= 1 {
             self.block[offset] = value;
             Ok(value)
         } else {
-            Err(())
+            Err(self.out_of_bounds(offset, 1))
         }
     }
 
     #[inline]
-    pub fn write16(&mut self, offset: usize, value: u16) -> Result<u16, ()> {
+    pub fn write16(&mut self, offset: usize, value: u16) -> Result<u16, HarmError> {
         if self.get_available_space(offset) >= 2 {
             unsafe { 
                 *(&mut self.block[offset] as *mut u8 as *mut u16) = value;
             }
             Ok(value)
         } else {
-            Err(())
+            Err(self.out_of_bounds(offset, 2))
         }
     }
 
     #[inline]
-    pub fn write32(&mut self, offset: usize, value: u32) -> Result<u32, ()> {
+    pub fn write32(&mut self, offset: usize, value: u32) -> Result<u32, HarmError> {
         if self.get_available_space(offset) >= 4 {
             unsafe { 
                 *(&mut self.block[offset] as *mut u8 as *mut u32) = value;
             }
             Ok(value)
         } else {
-            Err(())
+            Err(self.out_of_bounds(offset, 4))
         }
     }
 
-    pub fn fill(&mut self, code: &[u8]) -> Result<(), ()> {
+    pub fn fill(&mut self, code: &[u8]) -> Result<(), HarmError> {
         if self.size() >= code.len() {
-            self.block[..].copy_from_slice(&code[..]);
+            self.block.copy_from_slice(code);
             Ok(())
         } else {
-            Err(())
+            Err(self.out_of_bounds(0, code.len()))
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,118 @@
+use super::adjustment::{aligned_pc, detect_wide_branch, halfwords, pc, RelocKind, TRAMPOLINE_LDR};
+
+/// Sign-extend the low `bits` bits of `value`
+#[inline]
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    ((value << (32 - bits)) as i32) >> (32 - bits)
+}
+
+#[allow(non_snake_case)]
+fn decode_B_T1(src_code: u32, src_addr: usize) -> usize {
+    (pc(src_addr) + sign_extend((src_code & 0xff) << 1, 9)) as usize
+}
+
+#[allow(non_snake_case)]
+fn decode_B_T2(src_code: u32, src_addr: usize) -> usize {
+    (pc(src_addr) + sign_extend((src_code & 0x7ff) << 1, 12)) as usize
+}
+
+#[allow(non_snake_case)]
+fn decode_B_T3(src_code: u32, src_addr: usize) -> usize {
+    let (hw1, hw2) = halfwords(src_code);
+    let offset = ((hw1 >> 10) & 1) << 20
+        | ((hw2 >> 11) & 1) << 19
+        | ((hw2 >> 13) & 1) << 18
+        | (hw1 & 0b111111) << 12
+        | (hw2 & 0b11111111111) << 1;
+
+    (pc(src_addr) + sign_extend(offset, 21)) as usize
+}
+
+fn decode_long_offset(src_code: u32) -> i32 {
+    let (hw1, hw2) = halfwords(src_code);
+    let s = (hw1 >> 10) & 1;
+    let i1 = !((hw2 >> 13) ^ s) & 1;
+    let i2 = !((hw2 >> 11) ^ s) & 1;
+    let offset = s << 24 | i1 << 23 | i2 << 22 | (hw1 & 0b1111111111) << 12 | (hw2 & 0b11111111111) << 1;
+
+    sign_extend(offset, 25)
+}
+
+#[allow(non_snake_case)]
+fn decode_B_T4(src_code: u32, src_addr: usize) -> usize {
+    (pc(src_addr) + decode_long_offset(src_code)) as usize
+}
+
+#[allow(non_snake_case)]
+fn decode_BLX(src_code: u32, src_addr: usize) -> usize {
+    (aligned_pc(src_addr) + decode_long_offset(src_code & !(1 << 16))) as usize
+}
+
+#[allow(non_snake_case)]
+fn decode_CBZ(src_code: u32, src_addr: usize) -> usize {
+    let offset = ((src_code >> 9) & 1) << 6 | ((src_code >> 3) & 0b11111) << 1;
+    pc(src_addr) as usize + offset as usize
+}
+
+fn decode_imm8_literal(src_code: u32, src_addr: usize) -> usize {
+    aligned_pc(src_addr) as usize + ((src_code & 0xff) << 2) as usize
+}
+
+#[allow(non_snake_case)]
+fn decode_ADR_W(src_code: u32, src_addr: usize) -> usize {
+    let (hw1, hw2) = halfwords(src_code);
+    let imm12 = ((hw1 >> 10) & 1) << 11 | ((hw2 >> 12) & 0b111) << 8 | (hw2 & 0xff);
+
+    if hw1 & 0b1010_0000 != 0 {
+        (aligned_pc(src_addr) - imm12 as i32) as usize
+    } else {
+        (aligned_pc(src_addr) + imm12 as i32) as usize
+    }
+}
+
+#[allow(non_snake_case)]
+fn decode_LDR_W(src_code: u32, src_addr: usize) -> usize {
+    let (hw1, hw2) = halfwords(src_code);
+    let imm12 = (hw2 & 0xfff) as i32;
+
+    if hw1 & (1 << 7) != 0 {
+        (aligned_pc(src_addr) + imm12) as usize
+    } else {
+        (aligned_pc(src_addr) - imm12) as usize
+    }
+}
+
+fn decode_imm16(src_code: u32) -> u32 {
+    let (hw1, hw2) = halfwords(src_code);
+    (hw1 & 0xf) << 12 | ((hw1 >> 10) & 1) << 11 | ((hw2 >> 12) & 0b111) << 8 | (hw2 & 0xff)
+}
+
+/// Address `src_code`, an instruction of `kind` at `src_addr`, refers to
+///
+/// `MOVW` and `MOVT` only give the half of the address they hold.
+pub fn decode(kind: RelocKind, src_code: u32, src_addr: usize) -> usize {
+    match kind {
+        RelocKind::B_T1 => decode_B_T1(src_code, src_addr),
+        RelocKind::B_T2 => decode_B_T2(src_code, src_addr),
+        RelocKind::B_T3 => decode_B_T3(src_code, src_addr),
+        RelocKind::B_T4 | RelocKind::BL => decode_B_T4(src_code, src_addr),
+        RelocKind::BLX => decode_BLX(src_code, src_addr),
+        RelocKind::CBZ => decode_CBZ(src_code, src_addr),
+        RelocKind::ADR_T1 | RelocKind::LDR_T1 => decode_imm8_literal(src_code, src_addr),
+        RelocKind::ADR_W => decode_ADR_W(src_code, src_addr),
+        RelocKind::LDR_W => decode_LDR_W(src_code, src_addr),
+        RelocKind::MOVW => decode_imm16(src_code) as usize,
+        RelocKind::MOVT => (decode_imm16(src_code) << 16) as usize,
+        RelocKind::B_W => decode(detect_wide_branch(src_code), src_code, src_addr),
+    }
+}
+
+
+/// Target of the trampoline made of `code`, if it is one
+pub fn decode_trampoline(code: [u32; 2]) -> Option<usize> {
+    if code[0] == TRAMPOLINE_LDR {
+        Some(code[1] as usize & !1)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,70 @@
+use core::fmt;
+
+/// Entropy of the 256-bit seed each layout is drawn from, in 1/256 bits
+pub const SEED_ENTROPY: u32 = 256 << 8;
+
+/// Entropy of a layout, in 1/256 bits
+///
+/// `order` and `padding` count every layout the shuffle could draw, while the
+/// layout is drawn from a seed: no layout has more entropy than the seed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Entropy {
+    /// Contributed by the order of the objects
+    pub order: u32,
+    /// Contributed by the random gaps between objects
+    pub padding: u32,
+}
+
+impl Entropy {
+    /// Entropy of the whole layout, at most `SEED_ENTROPY`
+    #[inline]
+    pub fn total(&self) -> u32 {
+        self.order.saturating_add(self.padding).min(SEED_ENTROPY)
+    }
+
+    /// Whether the order and padding could draw more layouts than there are seeds
+    #[inline]
+    pub fn is_capped(&self) -> bool {
+        self.order.saturating_add(self.padding) > SEED_ENTROPY
+    }
+
+    /// Whole bits of entropy, rounded down
+    #[inline]
+    pub fn bits(&self) -> u32 {
+        self.total() >> 8
+    }
+}
+
+impl fmt::Display for Entropy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hundredths = |q8: u32| ((q8 & 0xff) * 100) >> 8;
+        write!(f, "{}.{:02} bits{} (order {}.{:02}, padding {}.{:02})",
+               self.bits(), hundredths(self.total()),
+               if self.is_capped() { ", capped by the seed" } else { "" },
+               self.order >> 8, hundredths(self.order),
+               self.padding >> 8, hundredths(self.padding))
+    }
+}
+
+/// `log2(x)` in 1/256 bits, rounded down, `log2(0)` is taken as 0
+pub fn log2_q8(x: u32) -> u32 {
+    if x == 0 {
+        return 0;
+    }
+
+    let int = 31 - x.leading_zeros();
+    // mantissa in [1, 2) with 31 fractional bits
+    let mut y = (x as u64) << (31 - int);
+    let mut frac = 0;
+
+    for _ in 0 .. 8 {
+        y = (y * y) >> 31;
+        frac <<= 1;
+        if y >= 1 << 32 {
+            y >>= 1;
+            frac |= 1;
+        }
+    }
+
+    (int << 8) | frac
+}
@@ -0,0 +1,121 @@
+use core::fmt;
+
+use super::adjustment::RelocError;
+
+/// Errors of the secure runtime
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HarmError {
+    /// Object `object` does not fit in what is left of the sandbox bank
+    SandboxExhausted {
+        object: u16,
+        needed: usize,
+        left: usize,
+    },
+    /// Access of `length` bytes at `address` outside of a code block
+    OutOfBounds {
+        address: usize,
+        length: usize,
+    },
+    /// The reloc item at `offset` of object `object` cannot be applied to its target
+    InvalidRelocation {
+        object: u16,
+        offset: u16,
+    },
+    /// The branch at `offset` of object `object` cannot reach its target, even through a trampoline
+    BranchOutOfRange {
+        object: u16,
+        offset: u16,
+        error: RelocError,
+    },
+    /// The metadata contradicts itself about object `object`
+    MetadataInconsistency {
+        object: u16,
+        reason: &'static str,
+    },
+    /// Object 0 is not the vector table of the non-secure firmware
+    MissingVectorTable,
+    /// The binary metadata image is broken at byte `offset`
+    MalformedImage {
+        offset: usize,
+        reason: &'static str,
+    },
+    /// The signature of the binary metadata image does not match its content or key
+    BadSignature,
+    /// A table trusted by the non-secure callable veneers at `address` is reachable from the non-secure world
+    ExposedTable {
+        address: usize,
+    },
+    /// The sandbox bank of `size` bytes at `base` cannot be used by the non-secure world
+    InvalidSandbox {
+        base: usize,
+        size: usize,
+        reason: &'static str,
+    },
+    /// The range of `size` bytes at `base` of the memory map cannot be given its TrustZone attribution
+    InvalidMemoryMap {
+        base: usize,
+        size: usize,
+        reason: &'static str,
+    },
+    /// SAU region `region` does not hold what the runtime programmed
+    SauMismatch {
+        region: u8,
+    },
+    /// The bits of the register at `address` checked by its rule read `actual` instead of `expected`
+    RegisterMismatch {
+        address: usize,
+        expected: u32,
+        actual: u32,
+    },
+    /// The range of `size` bytes at `base` cannot hold the initial non-secure stacks or heap
+    InvalidStackWindow {
+        base: usize,
+        size: usize,
+        reason: &'static str,
+    },
+}
+
+/// Object reported by `SandboxExhausted` when a trampoline does not fit
+pub const TRAMPOLINE_OBJECT: u16 = u16::MAX;
+
+impl fmt::Display for HarmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            HarmError::SandboxExhausted { object: TRAMPOLINE_OBJECT, needed, left } => {
+                write!(f, "sandbox exhausted by a trampoline ({} bytes needed, {} left)", needed, left)
+            },
+            HarmError::SandboxExhausted { object, needed, left } => {
+                write!(f, "sandbox exhausted by object {} ({} bytes needed, {} left)", object, needed, left)
+            },
+            HarmError::OutOfBounds { address, length } => {
+                write!(f, "out-of-bounds access of {} bytes at 0x{:x}", length, address)
+            },
+            HarmError::InvalidRelocation { object, offset } => {
+                write!(f, "invalid relocation at offset {} of object {}", offset, object)
+            },
+            HarmError::BranchOutOfRange { object, offset, error } => {
+                write!(f, "branch at offset {} of object {} out of range ({:?})", offset, object, error)
+            },
+            HarmError::MetadataInconsistency { object, reason } => {
+                write!(f, "inconsistent metadata for object {}: {}", object, reason)
+            },
+            HarmError::MissingVectorTable => write!(f, "object 0 is not the vector table"),
+            HarmError::MalformedImage { offset, reason } => write!(f, "malformed metadata image at byte {}: {}", offset, reason),
+            HarmError::BadSignature => write!(f, "bad metadata signature"),
+            HarmError::ExposedTable { address } => write!(f, "table at 0x{:x} is reachable from the non-secure world", address),
+            HarmError::InvalidSandbox { base, size, reason } => {
+                write!(f, "invalid sandbox bank of {} bytes at 0x{:x}: {}", size, base, reason)
+            },
+            HarmError::InvalidMemoryMap { base, size, reason } => {
+                write!(f, "invalid memory map range of {} bytes at 0x{:x}: {}", size, base, reason)
+            },
+            HarmError::SauMismatch { region } => write!(f, "SAU region {} differs from the configuration", region),
+            HarmError::RegisterMismatch { address, expected, actual } => {
+                write!(f, "register at 0x{:x} reads 0x{:x} instead of 0x{:x}", address, actual, expected)
+            },
+            HarmError::InvalidStackWindow { base, size, reason } => {
+                write!(f, "invalid stack window of {} bytes at 0x{:x}: {}", size, base, reason)
+            },
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Signed binary metadata placed alongside the non-secure firmware in the flash
+//!
+//! The image holds the same tables as the generated `obj_tbl.rs`, `adj_tbl.rs`
+//! and `ret_tbl.rs`, so the non-secure firmware can be updated without
+//! rebuilding the secure runtime. All fields are little-endian:
+//!
+//! ```text
+//! header     magic "HARM", version: u16, objects: u16, branches: u16, vectors: u16, callsites: u16, reserved: u16
+//! object     address: u32, size: u16, index: u16, first reloc item: u16, end of reloc items: u16, kind: u8, reserved: [u8; 3]
+//! branch     src offset: u16, dst index: u16, dst offset: u16, kind: u8, reserved: u8
+//! vector     object: u16, vector number: u16
+//! callsite   offset: u16, caller: u16
+//! signature  Ed25519 signature of all of the above, 64 bytes
+//! ```
+//!
+//! Branch and callsite records are laid out like `Branch` and `Callsite`, so
+//! the randomizer uses them in place. Only the objects and the vectors, a
+//! small part of the image, are decoded into tables of their own.
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::{align_of, size_of};
+use core::slice;
+
+use ed25519_compact::{KeyPair, PublicKey, Seed, Signature};
+
+use super::adjustment::{Branch, RelocKind};
+use super::error::HarmError;
+use super::metadata::Metadata;
+use super::objects::{Callsite, Object, ObjectKind};
+use super::platform::Platform;
+
+pub const MAGIC: [u8; 4] = *b"HARM";
+pub const VERSION: u16 = 1;
+
+pub const HEADER_SIZE: usize = 16;
+pub const OBJECT_SIZE: usize = 16;
+pub const BRANCH_SIZE: usize = 8;
+pub const VECTOR_SIZE: usize = 4;
+pub const CALLSITE_SIZE: usize = 4;
+
+pub const PUBLIC_KEY_SIZE: usize = 32;
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// Codes of the relocation kinds are `0 .. NUM_OF_RELOC_KINDS`, `B_W` being 0
+const NUM_OF_RELOC_KINDS: u8 = RelocKind::MOVT as u8 + 1;
+
+// records used in place must match the types exactly
+const _: () = assert!(size_of::<Branch>() == BRANCH_SIZE && size_of::<Callsite>() == CALLSITE_SIZE);
+const _: () = assert!(HEADER_SIZE % align_of::<Branch>() == 0 && OBJECT_SIZE % align_of::<Branch>() == 0);
+
+fn malformed(offset: usize, reason: &'static str) -> HarmError {
+    HarmError::MalformedImage { offset, reason }
+}
+
+#[inline]
+fn read16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[inline]
+fn read32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// Size of the image starting with `header`, without its signature
+pub fn image_size(header: &[u8]) -> Result<usize, HarmError> {
+    if header.len() < HEADER_SIZE {
+        return Err(malformed(header.len(), "truncated header"));
+    }
+    if header[0 .. 4] != MAGIC {
+        return Err(malformed(0, "bad magic"));
+    }
+    if read16(header, 4) != VERSION {
+        return Err(malformed(4, "unsupported version"));
+    }
+
+    let count = |offset| read16(header, offset) as usize;
+    Ok(HEADER_SIZE + count(6) * OBJECT_SIZE + count(8) * BRANCH_SIZE + count(10) * VECTOR_SIZE + count(12) * CALLSITE_SIZE)
+}
+
+/// Binary metadata image, read in place
+#[derive(Clone, Copy)]
+pub struct Image<'a> {
+    bytes: &'a [u8],
+    num_of_objects: usize,
+    num_of_branches: usize,
+    num_of_vectors: usize,
+    num_of_callsites: usize,
+}
+
+impl<'a> Image<'a> {
+    /// Check the encoding of `bytes`, nothing is copied
+    ///
+    /// The image must be 2-byte aligned. Only the encoding is checked, run
+    /// `validate_metadata()` on the tables.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, HarmError> {
+        let size = image_size(bytes)?;
+        if bytes.len() != size {
+            return Err(malformed(bytes.len().min(size), "size does not match the header"));
+        }
+        if bytes.as_ptr() as usize % align_of::<Branch>() != 0 {
+            return Err(malformed(0, "misaligned image"));
+        }
+
+        let image = Image {
+            bytes,
+            num_of_objects: read16(bytes, 6) as usize,
+            num_of_branches: read16(bytes, 8) as usize,
+            num_of_vectors: read16(bytes, 10) as usize,
+            num_of_callsites: read16(bytes, 12) as usize,
+        };
+
+        for i in 0 .. image.num_of_objects {
+            let offset = image.object_offset(i) + 12;
+            if image.bytes[offset] > 3 {
+                return Err(malformed(offset, "unknown object kind"));
+            }
+        }
+        // a branch is only used in place once its kind is known to be a valid `RelocKind`
+        for i in 0 .. image.num_of_branches {
+            let offset = image.branch_offset() + i * BRANCH_SIZE + 6;
+            if image.bytes[offset] >= NUM_OF_RELOC_KINDS {
+                return Err(malformed(offset, "unknown relocation kind"));
+            }
+        }
+        for i in 0 .. image.num_of_vectors {
+            let offset = image.vector_offset() + i * VECTOR_SIZE;
+            if read16(image.bytes, offset) as usize >= image.num_of_objects {
+                return Err(malformed(offset, "vector of no object"));
+            }
+        }
+
+        Ok(image)
+    }
+
+    #[inline]
+    fn object_offset(&self, index: usize) -> usize {
+        HEADER_SIZE + index * OBJECT_SIZE
+    }
+
+    #[inline]
+    fn branch_offset(&self) -> usize {
+        self.object_offset(self.num_of_objects)
+    }
+
+    #[inline]
+    fn vector_offset(&self) -> usize {
+        self.branch_offset() + self.num_of_branches * BRANCH_SIZE
+    }
+
+    #[inline]
+    fn callsite_offset(&self) -> usize {
+        self.vector_offset() + self.num_of_vectors * VECTOR_SIZE
+    }
+
+    #[inline]
+    pub fn num_of_objects(&self) -> usize {
+        self.num_of_objects
+    }
+
+    /// Object `index`, decoded from its record
+    pub fn object(&self, index: usize) -> ObjectKind {
+        let offset = self.object_offset(index);
+        let (start, end) = (read16(self.bytes, offset + 8), read16(self.bytes, offset + 10));
+        let object = Object {
+            reloc_items: if start == end { None } else { Some((start, end)) },
+            address: read32(self.bytes, offset) as usize,
+            size: read16(self.bytes, offset + 4),
+            index: read16(self.bytes, offset + 6),
+        };
+
+        match self.bytes[offset + 12] {
+            0 => ObjectKind::VectorTable(object),
+            1 => ObjectKind::Function(object),
+            2 => ObjectKind::BasicBlock(object),
+            _ => ObjectKind::Data(object),
+        }
+    }
+
+    /// Branch records, in place
+    pub fn branches(&self) -> &'a [Branch] {
+        // SAFETY: aligned and sized like `Branch`, with valid kinds, checked by `new()`
+        unsafe { slice::from_raw_parts(self.bytes[self.branch_offset() ..].as_ptr() as *const Branch, self.num_of_branches) }
+    }
+
+    /// `(object, vector number)` of each interrupt service routine
+    pub fn vectors(&self) -> impl Iterator<Item = (u16, u16)> + 'a {
+        let (bytes, offset) = (self.bytes, self.vector_offset());
+        (0 .. self.num_of_vectors).map(move |i| (read16(bytes, offset + i * VECTOR_SIZE), read16(bytes, offset + i * VECTOR_SIZE + 2)))
+    }
+
+    /// Callsite records, in place
+    pub fn callsites(&self) -> &'a [Callsite] {
+        // SAFETY: aligned and sized like `Callsite`, checked by `new()`
+        unsafe { slice::from_raw_parts(self.bytes[self.callsite_offset() ..].as_ptr() as *const Callsite, self.num_of_callsites) }
+    }
+
+    /// Tables of the randomizer, the objects and the vectors are decoded and leaked like the static ones of the runtime
+    pub fn to_metadata(&self) -> Metadata<'a> {
+        let objects: &'static [ObjectKind] = Box::leak((0 .. self.num_of_objects).map(|i| self.object(i)).collect::<Vec<_>>().into_boxed_slice());
+        let vectors: Vec<(&'static ObjectKind, u16)> = self.vectors().map(|(isr, vector)| (&objects[isr as usize], vector)).collect();
+
+        Metadata {
+            objects,
+            branches: self.branches(),
+            vectors: Box::leak(vectors.into_boxed_slice()),
+            callsites: self.callsites(),
+        }
+    }
+}
+
+/// Read the tables of `image`, see `Image`
+pub fn parse(image: &[u8]) -> Result<Metadata<'_>, HarmError> {
+    Ok(Image::new(image)?.to_metadata())
+}
+
+/// Encode `metadata` as an unsigned image
+pub fn serialize(metadata: &Metadata) -> Vec<u8> {
+    let mut image = Vec::new();
+    let count = |n: usize| (n as u16).to_le_bytes();
+
+    image.extend_from_slice(&MAGIC);
+    image.extend_from_slice(&VERSION.to_le_bytes());
+    image.extend_from_slice(&count(metadata.objects.len()));
+    image.extend_from_slice(&count(metadata.branches.len()));
+    image.extend_from_slice(&count(metadata.vectors.len()));
+    image.extend_from_slice(&count(metadata.callsites.len()));
+    image.extend_from_slice(&[0; 2]);
+
+    for object in metadata.objects.iter() {
+        let obj = object.get_object();
+        let (start, end) = obj.reloc_items.unwrap_or((0, 0));
+        let kind = match object {
+            ObjectKind::VectorTable(_) => 0,
+            ObjectKind::Function(_) => 1,
+            ObjectKind::BasicBlock(_) => 2,
+            ObjectKind::Data(_) => 3,
+        };
+        image.extend_from_slice(&(obj.address as u32).to_le_bytes());
+        image.extend_from_slice(&obj.size.to_le_bytes());
+        image.extend_from_slice(&obj.index.to_le_bytes());
+        image.extend_from_slice(&start.to_le_bytes());
+        image.extend_from_slice(&end.to_le_bytes());
+        image.extend_from_slice(&[kind, 0, 0, 0]);
+    }
+
+    for branch in metadata.branches.iter() {
+        image.extend_from_slice(&branch.src_offset.to_le_bytes());
+        image.extend_from_slice(&branch.dst_object.to_le_bytes());
+        image.extend_from_slice(&branch.dst_offset.to_le_bytes());
+        image.extend_from_slice(&[branch.kind as u8, 0]);
+    }
+
+    for &(isr, vector) in metadata.vectors.iter() {
+        image.extend_from_slice(&isr.get_object().index.to_le_bytes());
+        image.extend_from_slice(&vector.to_le_bytes());
+    }
+
+    for callsite in metadata.callsites.iter() {
+        image.extend_from_slice(&callsite.offset.to_le_bytes());
+        image.extend_from_slice(&callsite.caller.to_le_bytes());
+    }
+
+    image
+}
+
+/// Public key matching the signing key derived from `seed`
+pub fn public_key(seed: &[u8; 32]) -> [u8; PUBLIC_KEY_SIZE] {
+    *KeyPair::from_seed(Seed::new(*seed)).pk
+}
+
+/// Sign `image` with the key derived from `seed`, the signature goes right after the image
+pub fn sign(image: &[u8], seed: &[u8; 32]) -> [u8; SIGNATURE_SIZE] {
+    *KeyPair::from_seed(Seed::new(*seed)).sk.sign(image, None)
+}
+
+/// Check that `signature` was made over `image` by the owner of `public_key`
+pub fn verify(image: &[u8], signature: &[u8], public_key: &[u8; PUBLIC_KEY_SIZE]) -> Result<(), HarmError> {
+    let signature = Signature::from_slice(signature).map_err(|_| HarmError::BadSignature)?;
+    PublicKey::new(*public_key).verify(image, &signature).map_err(|_| HarmError::BadSignature)
+}
+
+/// Verify and read the signed image at `address` of the flash
+///
+/// Nothing but the header is read before the signature is verified. An image
+/// the non-secure world can reach is copied to the heap first, so that it
+/// cannot be changed once verified.
+pub fn load_signed<P: Platform>(platform: &P, address: usize, public_key: &[u8; PUBLIC_KEY_SIZE]) -> Result<Metadata<'static>, HarmError> {
+    let size = image_size(unsafe { platform.memory(address, HEADER_SIZE) })?;
+    let mut signed: &'static [u8] = unsafe { platform.memory(address, size + SIGNATURE_SIZE) };
+
+    if !platform.is_secure(address, size + SIGNATURE_SIZE) {
+        // words keep the records aligned
+        let words = Box::leak(vec![0u32; (signed.len() + 3) / 4].into_boxed_slice());
+        let copy = unsafe { slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, signed.len()) };
+        copy.copy_from_slice(signed);
+        signed = copy;
+    }
+
+    verify(&signed[.. size], &signed[size ..], public_key)?;
+    parse(&signed[.. size])
+}
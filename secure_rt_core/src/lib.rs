@@ -0,0 +1,37 @@
+//! Hardware independent core of the HARM secure runtime
+//!
+//! The randomizer copies every object of the non-secure firmware into a
+//! sandbox in a random order, then rewrites the branches and the vector table
+//! so that they refer to the new locations. All accesses to the hardware go
+//! through the `Platform` trait and the randomness comes from a `RandomSource`,
+//! so the whole pipeline also runs on a host.
+#![no_std]
+
+extern crate alloc;
+
+pub mod objects;
+pub mod sandbox;
+pub mod adjustment;
+pub mod decoder;
+pub mod codeblock;
+pub mod rb_tree;
+pub mod metadata;
+pub mod platform;
+pub mod random;
+pub mod randomizer;
+pub mod ns_stack;
+pub mod entropy;
+pub mod error;
+pub mod image;
+pub mod trustzone;
+pub mod stacks;
+
+pub use entropy::Entropy;
+pub use error::HarmError;
+pub use metadata::{check_execute_only, validate_metadata, Metadata};
+pub use objects::{Callsite, Object, ObjectKind};
+pub use platform::Platform;
+pub use random::{ChaChaDrbg, HardwareRng, RandomSource, SeededRandom};
+pub use randomizer::Randomizer;
+pub use sandbox::{check_banks, CodeProtection, PaddingPolicy, Trap, NUM_OF_BANKS, REGION_ALIGN};
+pub use stacks::{HeapPolicy, StackLayout, StackPolicy};
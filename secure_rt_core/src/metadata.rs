@@ -0,0 +1,250 @@
+use alloc::vec::Vec;
+
+use super::adjustment::{Branch, TRAMPOLINE_SIZE};
+use super::error::HarmError;
+use super::objects::{Callsite, Object, ObjectKind};
+use super::sandbox::{get_align_bits, REGION_ALIGN};
+
+/// Size of the `B.W` appended to a basic block that falls through to its successor
+pub const FALL_THROUGH_SIZE: usize = 4;
+
+/// Metadata of the non-secure firmware produced by `harm-rw`
+#[derive(Clone, Copy)]
+pub struct Metadata<'a> {
+    /// All objects, the vector table comes first
+    pub objects: &'a [ObjectKind],
+
+    /// Branches of all objects, each object refers to a range of them
+    pub branches: &'a [Branch],
+
+    /// Interrupt service routines and their vector numbers
+    pub vectors: &'a [(&'a ObjectKind, u16)],
+
+    /// Return sites, indexed by callsite id
+    pub callsites: &'a [Callsite],
+}
+
+impl<'a> Metadata<'a> {
+    /// Branch instructions of `object` that need to be adjusted
+    pub fn get_reloc_items(&self, object: &Object) -> Option<&'a [Branch]> {
+        if let Some(item) = object.reloc_items {
+            Some(&self.branches[item.0 as usize.. item.1 as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Fall-through edge of a basic block, i.e. the block its last instruction continues into
+    ///
+    /// `harm-rw` emits the edge as the last reloc item of the block, with a
+    /// source offset equal to the block size. The branch itself is not part of
+    /// the original code, it is appended when the block is placed.
+    pub fn get_fall_through(&self, object: &Object) -> Option<&'a Branch> {
+        let last = self.get_reloc_items(object)?.last()?;
+
+        if last.src_offset as usize == object.get_size() {
+            Some(last)
+        } else {
+            None
+        }
+    }
+
+    /// Size of `object` once placed in the sandbox, including its fall-through branch
+    pub fn get_instance_size(&self, object: &Object) -> usize {
+        match self.get_fall_through(object) {
+            Some(_) => object.get_size() + FALL_THROUGH_SIZE,
+            None => object.get_size(),
+        }
+    }
+
+    /// Space `object` may take in a sandbox bank, including the worst alignment padding
+    pub(crate) fn get_worst_size(&self, object: &ObjectKind) -> usize {
+        self.get_instance_size(object.get_object()) + (1 << get_align_bits(object)) - 1
+    }
+
+    /// Space all code objects may take in a sandbox bank
+    pub(crate) fn get_code_size(&self) -> usize {
+        self.objects.iter().filter(|object| !object.is_data()).map(|object| self.get_worst_size(object)).sum()
+    }
+
+    /// Space the trampolines of all branches that may not reach their target in a bank of `span` bytes may take
+    pub(crate) fn get_trampoline_size(&self, span: usize) -> usize {
+        let branches: usize = self.objects.iter().filter(|object| !object.is_data()).map(|object| {
+            let obj = object.get_object();
+            self.get_reloc_items(obj).unwrap_or(&[]).iter()
+                .filter(|item| item.kind.is_branch() && item.kind.may_miss(span) && !item.is_position_independent(obj.index, obj.get_size()))
+                .count()
+        }).sum();
+        // trampolines are word-aligned
+        branches * (TRAMPOLINE_SIZE + 3)
+    }
+
+    /// Size of the data area at the end of every bank, holding all `Data` objects
+    ///
+    /// Rounded up to `REGION_ALIGN`, so that the code and the data of a bank
+    /// can be protected apart.
+    pub fn get_data_size(&self) -> usize {
+        let size: usize = self.objects.iter().filter(|object| object.is_data()).map(|object| self.get_worst_size(object)).sum();
+        (size + REGION_ALIGN - 1) & !(REGION_ALIGN - 1)
+    }
+}
+
+fn inconsistent(object: usize, reason: &'static str) -> HarmError {
+    HarmError::MetadataInconsistency { object: object as u16, reason }
+}
+
+/// Check that the code objects of `metadata` can run from execute-only memory
+///
+/// Code must not load from code relative to the PC: its literal pools have to
+/// be `Data` objects. `MOVW`/`MOVT` may still build the address of a function,
+/// which is never read. Jump tables of `TBB`/`TBH` are not described by
+/// the metadata, the rewriter must not emit them.
+pub fn check_execute_only(metadata: &Metadata) -> Result<(), HarmError> {
+    for object in metadata.objects.iter().filter(|object| !object.is_data()) {
+        let obj = object.get_object();
+        let items = metadata.get_reloc_items(obj).unwrap_or(&[]);
+
+        let reads_code = |item: &Branch| {
+            item.kind.is_data_reference() && item.kind.is_pc_relative() && !matches!(metadata.objects.get(item.dst_object as usize), Some(dst) if dst.is_data())
+        };
+        if items.iter().any(reads_code) {
+            return Err(inconsistent(obj.index as usize, "literal pool inside the code"));
+        }
+    }
+    Ok(())
+}
+
+/// Check the metadata before it is trusted by the randomizer, returns every violation found
+///
+/// Object indices, reloc items, vectors and callsites must stay within their
+/// tables and objects, objects must not overlap in the flash, and all of them
+/// must fit in the smallest of the `(base, size)` sandbox banks in `regions`,
+/// the code along with the data area. Only data references may target `Data` objects.
+pub fn validate_metadata(metadata: &Metadata, regions: &[(usize, usize)]) -> Result<(), Vec<HarmError>> {
+    let objects = metadata.objects;
+    let mut violations = Vec::new();
+
+    for (i, object) in objects.iter().enumerate() {
+        let obj = object.get_object();
+
+        if obj.index as usize != i {
+            violations.push(inconsistent(i, "index does not match its position in the table"));
+        }
+        if let ObjectKind::VectorTable(_) = object {
+            if i != 0 {
+                violations.push(inconsistent(i, "second vector table"));
+            }
+        }
+        if object.is_data() && obj.reloc_items.is_some() {
+            violations.push(inconsistent(i, "data object with reloc items"));
+            continue;
+        }
+
+        let items = match obj.reloc_items {
+            Some((start, end)) if start > end || end as usize > metadata.branches.len() => {
+                violations.push(inconsistent(i, "reloc items out of the branch table"));
+                continue;
+            },
+            Some(_) => metadata.get_reloc_items(obj).unwrap(),
+            None => continue,
+        };
+
+        for (j, item) in items.iter().enumerate() {
+            let offset = item.src_offset as usize;
+
+            // only the last item may be the fall-through edge past the end of a block
+            if offset == obj.get_size() && j + 1 == items.len() {
+                if let ObjectKind::VectorTable(_) = object {
+                    violations.push(inconsistent(i, "vector table falls through"));
+                }
+            } else if offset + item.kind.size() > obj.get_size() {
+                violations.push(inconsistent(i, "reloc item past the end of its object"));
+            }
+
+            match objects.get(item.dst_object as usize) {
+                Some(ObjectKind::VectorTable(_)) => {
+                    violations.push(HarmError::InvalidRelocation { object: i as u16, offset: item.src_offset });
+                },
+                // data is never executed, fall-through edges are branches too
+                Some(ObjectKind::Data(_)) if !item.kind.is_data_reference() || offset == obj.get_size() => {
+                    violations.push(HarmError::InvalidRelocation { object: i as u16, offset: item.src_offset });
+                },
+                Some(target) if item.dst_offset as usize >= target.get_object().get_size() => {
+                    violations.push(inconsistent(i, "reloc item targets past the end of its object"));
+                },
+                Some(_) => (),
+                None => violations.push(inconsistent(i, "reloc item targets no object")),
+            }
+        }
+    }
+
+    // sort by address to find overlaps between neighbours
+    let mut extents: Vec<(usize, usize, usize)> = objects.iter().enumerate()
+        .map(|(i, object)| (object.get_object().get_address(), object.get_object().get_size(), i))
+        .collect();
+    extents.sort_unstable();
+    for pair in extents.windows(2) {
+        let ((address, size, _), (next, _, i)) = (pair[0], pair[1]);
+        if address + size > next {
+            violations.push(inconsistent(i, "overlaps another object in the flash"));
+        }
+    }
+
+    match objects.first() {
+        Some(ObjectKind::VectorTable(ns_vector_tbl)) => {
+            for &(isr, vector) in metadata.vectors.iter() {
+                let index = isr.get_object().index as usize;
+
+                match objects.get(index) {
+                    Some(ObjectKind::Function(_)) => (),
+                    _ => violations.push(inconsistent(index, "interrupt handler is not a function")),
+                }
+                // entry 0 is the initial stack pointer
+                if vector == 0 || (vector as usize + 1) << 2 > ns_vector_tbl.get_size() {
+                    violations.push(inconsistent(index, "vector number out of the vector table"));
+                }
+            }
+        },
+        _ => violations.push(HarmError::MissingVectorTable),
+    }
+
+    for callsite in metadata.callsites.iter() {
+        let caller = callsite.caller as usize;
+
+        match objects.get(caller) {
+            Some(ObjectKind::Function(obj)) | Some(ObjectKind::BasicBlock(obj)) => {
+                if callsite.offset as usize > obj.get_size() {
+                    violations.push(inconsistent(caller, "callsite past the end of its caller"));
+                }
+            },
+            _ => violations.push(inconsistent(caller, "callsite caller is not a function")),
+        }
+    }
+
+    // every object must fit in a bank even with the worst alignment padding, a broken object
+    // table makes the sizes meaningless
+    if violations.is_empty() {
+        let capacity = regions.iter().map(|&(_, size)| size).min().unwrap_or(0);
+        let data_size = metadata.get_data_size();
+        let mut left = capacity.saturating_sub(data_size);
+
+        if data_size > capacity {
+            let first = objects.iter().position(|object| object.is_data()).unwrap_or(0);
+            violations.push(HarmError::SandboxExhausted { object: first as u16, needed: data_size, left: capacity });
+        }
+        for (i, object) in objects.iter().enumerate().filter(|(_, object)| !object.is_data()) {
+            let needed = metadata.get_worst_size(object);
+            if needed > left {
+                violations.push(HarmError::SandboxExhausted { object: i as u16, needed, left });
+                break;
+            }
+            left -= needed;
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
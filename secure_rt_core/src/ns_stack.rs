@@ -0,0 +1,88 @@
+use super::codeblock::CodeBlock;
+use super::objects::ObjectKind;
+use super::platform::Platform;
+use super::random::RandomSource;
+use super::randomizer::Randomizer;
+
+/// Thumb bit of the stacked xPSR
+const XPSR_T: u32 = 1 << 24;
+
+/// Offsets of the stacked PC and xPSR in an exception frame
+const FRAME_PC: usize = 24;
+const FRAME_XPSR: usize = 28;
+
+impl<'a, P: Platform, R: RandomSource> Randomizer<'a, P, R> {
+    /// Find the live function containing `addr`, returns its index and the offset of `addr`
+    pub fn locate(&self, addr: usize) -> Option<(usize, usize)> {
+        match self.lookup(addr) {
+            Some((ObjectKind::Function(obj), offset)) | Some((ObjectKind::BasicBlock(obj), offset)) => {
+                Some((obj.index as usize, offset))
+            },
+            _ => None,
+        }
+    }
+
+    /// Address `addr` will have once the staged layout is committed
+    ///
+    /// A PC about to run a trampoline moves straight to the new instance of its target.
+    pub fn relocate(&self, addr: usize) -> Option<usize> {
+        if let Some(target) = self.sandbox.get_trampoline_target(addr) {
+            return self.relocate(target);
+        }
+        self.locate(addr).map(|(index, offset)| self.get_staged_address(index) + offset)
+    }
+
+    /// Check whether `offset` in object `caller` is a return site
+    fn is_callsite(&self, caller: usize, offset: usize) -> bool {
+        self.return_sites.binary_search(&(caller as u16, offset as u16)).is_ok()
+    }
+
+    /// Move the code addresses of the non-secure stack between `frame` and `top` to the staged layout
+    ///
+    /// `frame` is the exception frame the hardware stacked on entry to the
+    /// handler calling the runtime. Two kinds of words are rewritten:
+    /// - the stacked PC of that frame, whatever instruction it points to, and
+    /// - return addresses (Thumb bit set) that point to a known callsite.
+    ///
+    /// No other word is taken for a PC, since locals and spilled data cannot be
+    /// told apart from one. A local that happens to equal a return address
+    /// with its Thumb bit set is still rewritten.
+    ///
+    /// Must run with interrupts disabled, right before the staged layout is committed.
+    ///
+    /// # Safety
+    ///
+    /// `frame .. top` must be mapped memory of the non-secure stack, nothing
+    /// else may access it during the call, and `frame` must point to the
+    /// exception frame, or the stacked PC of a word that is not one is rewritten.
+    pub unsafe fn fixup_stack(&self, frame: usize, top: usize) {
+        if frame >= top {
+            return;
+        }
+
+        let mut stack = CodeBlock::new(frame, self.platform.memory(frame, top - frame));
+
+        if let (Ok(pc), Ok(xpsr)) = (stack.read32(FRAME_PC), stack.read32(FRAME_XPSR)) {
+            if xpsr & XPSR_T != 0 {
+                if let Some(new_pc) = self.relocate(pc as usize) {
+                    stack.write32(FRAME_PC, new_pc as u32).unwrap();
+                }
+            }
+        }
+
+        let mut offset = 0;
+        while let Ok(word) = stack.read32(offset) {
+            let word = word as usize;
+
+            if word & 1 != 0 && offset != FRAME_PC {
+                if let Some((index, at)) = self.locate(word & !1) {
+                    if self.is_callsite(index, at) {
+                        stack.write32(offset, ((self.get_staged_address(index) + at) | 1) as u32).unwrap();
+                    }
+                }
+            }
+
+            offset += 4;
+        }
+    }
+}
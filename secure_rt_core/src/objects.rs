@@ -0,0 +1,56 @@
+#[repr(C)]
+pub struct Callsite {
+    pub offset: u16,
+    pub caller: u16,
+} 
+
+
+/// Object Description
+#[repr (C)]
+pub struct Object {
+    /// Branch instructions that need to be adjusted
+    pub reloc_items: Option<(u16, u16)>,
+    /// Original address in the flash
+    pub address: usize,
+    /// Object size
+    pub size: u16,
+    /// Index of this object
+    pub index: u16,
+}
+
+#[repr (C)]
+pub enum ObjectKind {
+    VectorTable(Object),
+    /// Function, or the entry block of a function split into basic blocks
+    Function(Object),
+    /// Any other basic block of a split function, placed independently of its function
+    BasicBlock(Object),
+    /// Literal pool moved out of the code, never executed and placed in the data area of the sandbox
+    Data(Object),
+}
+
+impl ObjectKind {
+    #[inline]
+    pub fn get_object(&self) -> &Object {
+        match self {
+            ObjectKind::VectorTable(obj) | ObjectKind::Function(obj) | ObjectKind::BasicBlock(obj) | ObjectKind::Data(obj) => obj,
+        }
+    }
+
+    /// Whether the object is data rather than code
+    #[inline]
+    pub fn is_data(&self) -> bool {
+        matches!(self, ObjectKind::Data(_))
+    }
+}
+
+impl Object {
+    #[inline]
+    pub fn get_address(&self) -> usize {
+        self.address
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.size as usize
+    }
+}
@@ -0,0 +1,44 @@
+use super::sandbox::NUM_OF_BANKS;
+
+/// Hardware abstraction of the secure runtime
+pub trait Platform {
+    /// Point the vector table of the non-secure world to `address`
+    fn set_vtor(&mut self, address: usize);
+
+    /// Access `length` bytes of memory at `address`
+    ///
+    /// # Safety
+    ///
+    /// The `length` bytes at `address` must exist and stay mapped for `'m`. The
+    /// slice may overlap other slices returned for the same memory, but the
+    /// caller must not use it while any of them is read or written, nor let it
+    /// outlive the memory.
+    unsafe fn memory<'m>(&self, address: usize, length: usize) -> &'m mut [u8];
+
+    /// Run `f` with interrupts disabled
+    fn critical_section<R, F: FnOnce() -> R>(f: F) -> R;
+
+    /// Let the non-secure world execute bank `active` of the `(base, size)` sandbox `banks` and nothing else of them
+    ///
+    /// No bank is ever writable by the non-secure world, and with `active` set
+    /// to `None` the whole sandbox is read-only and never executable. The last
+    /// `data_size` bytes of each bank hold the `Data` objects, they are never
+    /// executable either.
+    /// Called with interrupts disabled, along with the switch of the vector table.
+    fn protect_sandbox(&mut self, _banks: [(usize, usize); NUM_OF_BANKS], _active: Option<usize>, _data_size: usize) {}
+
+    /// Let the non-secure world execute the original firmware, whose objects lie in the `(base, size)` range `firmware`
+    ///
+    /// Called with interrupts disabled by `commit_original()`, after the sandbox
+    /// was made never executable.
+    fn execute_original(&mut self, _firmware: (usize, usize)) {}
+
+    /// Whether the `length` bytes at `address` are out of reach of the non-secure world
+    ///
+    /// Only a platform able to tell from its security configuration should
+    /// answer `true`, by default every signed image is copied before it is
+    /// verified.
+    fn is_secure(&self, _address: usize, _length: usize) -> bool {
+        false
+    }
+}
@@ -0,0 +1,169 @@
+/// Source of random numbers used to shuffle the firmware
+pub trait RandomSource {
+    /// Get the next uniformly distributed 32-bit word
+    fn next_u32(&mut self) -> u32;
+
+    /// Fill `dest` with random bytes
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let word = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&word[.. chunk.len()]);
+        }
+    }
+
+    /// Get a uniformly distributed number in `0 .. bound`
+    ///
+    /// Unlike `next_u32() % bound`, this is free of modulo bias: the 32-bit word
+    /// is scaled to the range with a widening multiplication and the few words
+    /// that would make some results more likely are rejected.
+    fn next_below(&mut self, bound: u32) -> u32 {
+        assert!(bound > 0, "empty range");
+
+        // 2^32 mod bound, the number of words to reject
+        let threshold = bound.wrapping_neg() % bound;
+
+        loop {
+            let m = self.next_u32() as u64 * bound as u64;
+            if m as u32 >= threshold {
+                return (m >> 32) as u32;
+            }
+        }
+    }
+}
+
+impl<R: RandomSource + ?Sized> RandomSource for &mut R {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (**self).next_u32()
+    }
+}
+
+/// Hardware random number generator, any function returning fresh random words
+pub struct HardwareRng<F: FnMut() -> u32>(pub F);
+
+impl<F: FnMut() -> u32> RandomSource for HardwareRng<F> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.0)()
+    }
+}
+
+/// Number of rounds of the ChaCha block function
+const CHACHA_ROUNDS: usize = 20;
+
+/// "expand 32-byte k"
+const CHACHA_CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+/// Deterministic random bit generator producing the ChaCha20 key stream
+///
+/// The 256-bit key is the seed, the nonce is zero and the block counter is 64
+/// bits wide, so a seed yields 2^70 bytes before the stream repeats. Seeding
+/// it once from the hardware RNG is far cheaper than polling the RNG for every
+/// word of a shuffle.
+pub struct ChaChaDrbg {
+    key: [u32; 8],
+    counter: u64,
+    block: [u32; 16],
+    /// Next word of `block` to hand out, `block.len()` when exhausted
+    index: usize,
+}
+
+impl ChaChaDrbg {
+    /// Create a generator from a 256-bit seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let mut drbg = ChaChaDrbg { key: [0; 8], counter: 0, block: [0; 16], index: 16 };
+        drbg.set_key(&seed);
+        drbg
+    }
+
+    /// Create a generator seeded from `source`, typically the hardware RNG
+    pub fn from_source<R: RandomSource>(source: &mut R) -> Self {
+        let mut seed = [0u8; 32];
+        source.fill_bytes(&mut seed);
+        ChaChaDrbg::from_seed(seed)
+    }
+
+    /// Replace the seed with fresh entropy from `source`
+    pub fn reseed<R: RandomSource>(&mut self, source: &mut R) {
+        let mut seed = [0u8; 32];
+        source.fill_bytes(&mut seed);
+        self.set_key(&seed);
+    }
+
+    fn set_key(&mut self, seed: &[u8; 32]) {
+        for (word, bytes) in self.key.iter_mut().zip(seed.chunks(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        self.counter = 0;
+        self.index = self.block.len();
+    }
+
+    fn refill(&mut self) {
+        let mut input = [0u32; 16];
+        input[.. 4].copy_from_slice(&CHACHA_CONSTANTS);
+        input[4 .. 12].copy_from_slice(&self.key);
+        input[12] = self.counter as u32;
+        input[13] = (self.counter >> 32) as u32;
+
+        let mut x = input;
+        for _ in 0 .. CHACHA_ROUNDS / 2 {
+            // column rounds
+            quarter_round(&mut x, 0, 4, 8, 12);
+            quarter_round(&mut x, 1, 5, 9, 13);
+            quarter_round(&mut x, 2, 6, 10, 14);
+            quarter_round(&mut x, 3, 7, 11, 15);
+            // diagonal rounds
+            quarter_round(&mut x, 0, 5, 10, 15);
+            quarter_round(&mut x, 1, 6, 11, 12);
+            quarter_round(&mut x, 2, 7, 8, 13);
+            quarter_round(&mut x, 3, 4, 9, 14);
+        }
+
+        for i in 0 .. 16 {
+            self.block[i] = x[i].wrapping_add(input[i]);
+        }
+        self.counter = self.counter.wrapping_add(1);
+        self.index = 0;
+    }
+}
+
+#[inline(always)]
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]); x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]); x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+impl RandomSource for ChaChaDrbg {
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= self.block.len() {
+            self.refill();
+        }
+
+        let word = self.block[self.index];
+        self.index += 1;
+        word
+    }
+}
+
+/// Deterministic source for reproducible tests (SplitMix64), not suitable for deployment
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        SeededRandom { state: seed }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) >> 32) as u32
+    }
+}
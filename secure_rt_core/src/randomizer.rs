@@ -0,0 +1,467 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use super::adjustment::{self, RelocError, RelocKind};
+use super::codeblock::CodeBlock;
+#[cfg(feature = "verify-relocations")]
+use super::decoder;
+use super::entropy::{log2_q8, Entropy};
+use super::error::HarmError;
+use super::metadata::{check_execute_only, Metadata};
+use super::objects::{Object, ObjectKind};
+use super::platform::Platform;
+use super::random::RandomSource;
+use super::sandbox::{get_align_bits, CodeProtection, PaddingPolicy, SandBox, NUM_OF_BANKS};
+
+/// Space the code objects, and the trampolines `protection` allows them, take in the largest of `banks`
+fn reserved_size(metadata: &Metadata, banks: [(usize, usize); NUM_OF_BANKS], protection: CodeProtection) -> usize {
+    let span = banks.iter().map(|&(_, size)| size).max().unwrap_or(0);
+    let trampolines = if protection == CodeProtection::Readable { metadata.get_trampoline_size(span) } else { 0 };
+    metadata.get_code_size() + trampolines
+}
+
+/// Bytes of heap a randomizer keeps for `num_of_objects` objects and `num_of_callsites` callsites
+///
+/// Trampolines come on top of it, as they are placed.
+pub const fn heap_size(num_of_objects: usize, num_of_callsites: usize) -> usize {
+    SandBox::index_size(num_of_objects)
+        + num_of_objects * (size_of::<u32>() + size_of::<u16>())
+        + num_of_callsites * size_of::<(u16, u16)>()
+}
+
+/// Randomizer of the non-secure firmware
+pub struct Randomizer<'a, P: Platform, R: RandomSource> {
+    pub(crate) platform: P,
+
+    /// Entropy source of the shuffle
+    rng: R,
+
+    pub(crate) metadata: Metadata<'a>,
+
+    pub(crate) sandbox: SandBox<'a>,
+
+    /// Object addresses of the running layout, read by the non-secure callable veneers
+    pub(crate) dispatch_tbl: &'a mut [u32],
+
+    /// Object addresses of the layout being built, published to `dispatch_tbl` on commit
+    pub(crate) staging_tbl: Vec<u32>,
+
+    /// Order in which the objects are placed in the sandbox
+    shuffled_sequence: Vec<u16>,
+
+    /// `(caller, offset)` of all callsites, sorted for the stack walk
+    pub(crate) return_sites: Vec<(u16, u16)>,
+
+    /// Random gaps between objects
+    padding: PaddingPolicy,
+
+    /// Space all code objects and the trampolines they may need take in a bank with the worst alignment padding
+    reserved: usize,
+
+    /// Whether the code may read data embedded in it
+    protection: CodeProtection,
+
+    /// Entropy of the layout built last
+    entropy: Entropy,
+}
+
+impl<'a, P: Platform, R: RandomSource> Randomizer<'a, P, R> {
+    /// Create a randomizer placing objects in the `(base, size)` banks given by `regions`
+    ///
+    /// `dispatch_tbl` must hold one entry per object.
+    ///
+    /// # Safety
+    ///
+    /// The banks are taken over through `Platform::memory()` for `'a`: they must
+    /// exist, not overlap each other, the metadata or `dispatch_tbl`, and no
+    /// one but the randomizer may write them (or read them while it does) as
+    /// long as it lives. The non-secure world only runs the committed bank.
+    pub unsafe fn new(platform: P, rng: R, metadata: Metadata<'a>, regions: [(usize, usize); NUM_OF_BANKS],
+                      dispatch_tbl: &'a mut [u32]) -> Self {
+        let num_of_objects = metadata.objects.len();
+        let banks = regions.map(|(base, size)| (base, platform.memory(base, size)));
+        let mut return_sites: Vec<(u16, u16)> = metadata.callsites.iter().map(|cs| (cs.caller, cs.offset)).collect();
+        return_sites.sort_unstable();
+        let reserved = reserved_size(&metadata, regions, CodeProtection::Readable);
+        let mut sandbox = SandBox::new(banks, num_of_objects);
+        sandbox.set_data_size(metadata.get_data_size());
+
+        Randomizer {
+            sandbox,
+            platform,
+            rng,
+            metadata,
+            dispatch_tbl,
+            staging_tbl: (0 .. num_of_objects).map(|_| 0u32).collect(),
+            shuffled_sequence: (0 .. num_of_objects).map(|i| i as u16).collect(),
+            return_sites,
+            padding: PaddingPolicy::NONE,
+            reserved,
+            protection: CodeProtection::Readable,
+            entropy: Entropy::default(),
+        }
+    }
+
+    /// Choose the random gaps between objects, applies from the next `shuffle()`
+    pub fn set_padding(&mut self, padding: PaddingPolicy) {
+        self.padding = padding;
+        self.sandbox.set_trap(padding.trap);
+    }
+
+    /// Choose what the non-secure world may do with the code, applies from the next `ref_adjust()`
+    ///
+    /// `CodeProtection::ExecuteOnly` fails if an object reads data next to its code.
+    pub fn set_protection(&mut self, protection: CodeProtection) -> Result<(), HarmError> {
+        if protection == CodeProtection::ExecuteOnly {
+            check_execute_only(&self.metadata)?;
+        }
+        self.protection = protection;
+        self.reserved = reserved_size(&self.metadata, self.sandbox.banks(), protection);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn padding(&self) -> PaddingPolicy {
+        self.padding
+    }
+
+    #[inline]
+    pub fn protection(&self) -> CodeProtection {
+        self.protection
+    }
+
+    /// Bank the next `shuffle()` builds its layout in, the one following the running layout
+    ///
+    /// A layout dropped before `commit()` leaves it unchanged.
+    #[inline]
+    pub fn next_bank(&self) -> usize {
+        (self.sandbox.active() + 1) % NUM_OF_BANKS
+    }
+
+    /// Entropy of the layout built by the last `shuffle()`
+    #[inline]
+    pub fn entropy(&self) -> Entropy {
+        self.entropy
+    }
+
+    #[inline]
+    pub fn platform(&mut self) -> &mut P {
+        &mut self.platform
+    }
+
+    #[inline]
+    pub fn rng(&mut self) -> &mut R {
+        &mut self.rng
+    }
+
+    #[inline]
+    pub fn metadata(&self) -> &Metadata<'a> {
+        &self.metadata
+    }
+
+    /// Address of object `index` in the running layout
+    #[inline]
+    pub fn get_instance_address(&self, index: usize) -> usize {
+        self.dispatch_tbl[index] as usize
+    }
+
+    /// Address of object `index` in the layout being built
+    #[inline]
+    pub fn get_staged_address(&self, index: usize) -> usize {
+        self.staging_tbl[index] as usize
+    }
+
+    /// Running instance of `object`
+    pub fn get_instance(&self, object: &Object) -> CodeBlock<'a> {
+        let address = self.get_instance_address(object.index as usize);
+        CodeBlock::new(address, unsafe { self.platform.memory(address, self.metadata.get_instance_size(object)) })
+    }
+
+    fn get_staged_instance(&self, object: &Object) -> CodeBlock<'a> {
+        let address = self.get_staged_address(object.index as usize);
+        CodeBlock::new(address, unsafe { self.platform.memory(address, self.metadata.get_instance_size(object)) })
+    }
+
+    /// Original code of `object` in the flash
+    pub fn get_origin_code(&self, object: &Object) -> CodeBlock<'a> {
+        CodeBlock::new(object.address, unsafe { self.platform.memory(object.address, object.get_size()) })
+    }
+
+    fn get_shuffled_sequence(&mut self) -> &[u16] {
+        // start over from the identity, so that the order only depends on the random source
+        let seq = &mut self.shuffled_sequence;
+        for (i, obj_i) in seq.iter_mut().enumerate() {
+            *obj_i = i as u16;
+        }
+
+        // shuffle all objects (functions and vector table), if there are any
+        let mut i = seq.len().saturating_sub(1);
+        self.entropy.order = 0;
+
+        while i > 0 {
+            // pick uniformly among the first i + 1 objects
+            let j = self.rng.next_below((i + 1) as u32);
+            seq.swap(i, j as usize);
+            self.entropy.order += log2_q8((i + 1) as u32);
+            i -= 1;
+        }
+
+        &self.shuffled_sequence[..]
+    }
+
+    /// Place all objects in the sandbox in a random order
+    pub fn shuffle(&mut self) -> Result<(), HarmError> {
+        let objects = self.metadata.objects;
+
+        self.get_shuffled_sequence();
+        self.sandbox.reset();
+        self.entropy.padding = 0;
+
+        // space still needed by the objects not placed yet
+        let mut reserved = self.reserved;
+
+        // Shuffle all objects
+
+        for i in 0 .. self.shuffled_sequence.len() {
+            let obj_i = self.shuffled_sequence[i] as usize;
+            let object = &objects[obj_i];
+            let code = self.get_origin_code(object.get_object());
+            let size = self.metadata.get_instance_size(object.get_object());
+
+            // a random gap that leaves room for all remaining objects, data is packed in its own area; the
+            // gap is a multiple of the alignment of the object, so that every choice is a different address
+            let gap = if object.is_data() {
+                0
+            } else {
+                reserved -= self.metadata.get_worst_size(object);
+                let slack = self.sandbox.capacity().saturating_sub(reserved + self.metadata.get_worst_size(object));
+                let unit = 1 << get_align_bits(object);
+                let choices = (self.padding.max_gap.min(slack) / unit + 1) as u32;
+                self.entropy.padding += log2_q8(choices);
+                if choices > 1 { self.rng.next_below(choices) as usize * unit } else { 0 }
+            };
+
+            let new_addr = self.sandbox.push(object, code.block, size, gap)?;
+
+            // update the address of each object in the new layout
+            self.staging_tbl[obj_i] = new_addr as u32;
+        }
+
+        self.sandbox.finish();
+        Ok(())
+    }
+
+    /// Point the instruction of `kind` at `offset` of `cb` to `dst_addr`, through a trampoline if it cannot reach
+    fn relocate_reference(&mut self, object: &Object, cb: &mut CodeBlock, offset: usize, kind: RelocKind, src_code: u32,
+                          dst_addr: usize) -> Result<(), HarmError> {
+        let src_addr = cb.get_address(offset)?;
+        let kind = kind.resolve(src_code);
+        let reloc_error = |error| match error {
+            RelocError::OutOfRange { .. } => HarmError::BranchOutOfRange { object: object.index, offset: offset as u16, error },
+            RelocError::Misaligned { .. } => HarmError::InvalidRelocation { object: object.index, offset: offset as u16 },
+        };
+
+        let new_code = match adjustment::encode(kind, src_code, src_addr, dst_addr) {
+            // a trampoline holds its target as a literal, execute-only code cannot have one
+            Err(RelocError::OutOfRange { .. }) if kind.is_branch() && self.protection == CodeProtection::Readable => {
+                // share a trampoline in reach, or place a new one after the objects
+                let shared = self.sandbox.find_trampolines(dst_addr)
+                    .find_map(|trampoline| adjustment::encode(kind, src_code, src_addr, trampoline).ok());
+                match shared {
+                    Some(new_code) => new_code,
+                    None => {
+                        // the gaps leave room for it, a bank too small for it is not a branch error
+                        let trampoline = self.sandbox.push_trampoline(dst_addr)?;
+                        adjustment::encode(kind, src_code, src_addr, trampoline).map_err(reloc_error)?
+                    },
+                }
+            },
+            result => result.map_err(reloc_error)?,
+        };
+
+        if kind.size() == 2 {
+            cb.write16(offset, new_code as u16)?;
+        } else {
+            cb.write32(offset, new_code)?;
+        }
+        Ok(())
+    }
+
+    fn do_adjust(&mut self, object: &Object) -> Result<(), HarmError> {
+        let reloc_items = self.metadata.get_reloc_items(object);
+        if reloc_items.is_none() {
+            return Ok(());
+        }
+
+        // rewrite all location-sensitive instructions (i.e. branch instructions)
+
+        let mut cb = self.get_staged_instance(object);
+        let adjust_items = reloc_items.unwrap();
+
+        for adjust_item in adjust_items.iter() {
+            let offset = adjust_item.src_offset as usize;
+
+            // the whole object moves, relative references inside it still hold
+            if adjust_item.is_position_independent(object.index, object.get_size()) {
+                continue;
+            }
+
+            match self.metadata.objects.get(adjust_item.dst_object as usize) {
+                Some(ObjectKind::Function(target_func)) | Some(ObjectKind::BasicBlock(target_func)) | Some(ObjectKind::Data(target_func)) => {
+                    let dst_addr = self.get_staged_address(target_func.index as usize) + adjust_item.dst_offset as usize;
+
+                    let (kind, src_code) = if offset == object.get_size() {
+                        // fall-through edge, continue into the next block
+                        (RelocKind::B_T4, adjustment::FALL_THROUGH)
+                    } else if adjust_item.kind.size() == 2 {
+                        (adjust_item.kind, cb.read16(offset)? as u32)
+                    } else {
+                        (adjust_item.kind, cb.read32(offset)?)
+                    };
+
+                    self.relocate_reference(object, &mut cb, offset, kind, src_code, dst_addr)?;
+                },
+                Some(ObjectKind::VectorTable(_)) => {
+                    return Err(HarmError::InvalidRelocation { object: object.index, offset: offset as u16 });
+                },
+                None => {
+                    return Err(HarmError::MetadataInconsistency { object: object.index, reason: "reloc item targets no object" });
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rewrite the vector table and all branches of the layout being built
+    pub fn ref_adjust(&mut self) -> Result<(), HarmError> {
+        let objects = self.metadata.objects;
+
+        // update each entry of vector table
+        match objects.first() {
+            Some(ObjectKind::VectorTable(ns_vector_tbl)) => {
+                let mut ns_vector_inst = self.get_staged_instance(ns_vector_tbl);
+
+                for entry in self.metadata.vectors.iter() {
+                    if let ObjectKind::Function(isr) = entry.0 {
+                        let ns_vector_addr = self.get_staged_address(isr.index as usize);
+                        ns_vector_inst.write32((entry.1 << 2) as usize, (ns_vector_addr | 1usize) as u32)?;
+                    }
+                }
+            },
+
+            _ => return Err(HarmError::MissingVectorTable),
+        }
+
+        // update references in each function
+        for object in objects[1 ..].iter() {
+            match object {
+                ObjectKind::Function(ns_func_obj) | ObjectKind::BasicBlock(ns_func_obj) => self.do_adjust(ns_func_obj)?,
+                // literal pools hold no references
+                ObjectKind::Data(_) => (),
+                ObjectKind::VectorTable(obj) => {
+                    return Err(HarmError::MetadataInconsistency { object: obj.index, reason: "second vector table" });
+                },
+            }
+        }
+
+        #[cfg(feature = "verify-relocations")]
+        self.verify_relocations();
+
+        Ok(())
+    }
+
+    /// Check that every reference relocated by `ref_adjust()` lands on its target
+    ///
+    /// References going through a trampoline are followed. Panics with the
+    /// object index and offset of the first reference that does not decode
+    /// back to its target.
+    #[cfg(feature = "verify-relocations")]
+    pub fn verify_relocations(&self) {
+        for object in self.metadata.objects.iter() {
+            let obj = object.get_object();
+            let cb = self.get_staged_instance(obj);
+
+            for item in self.metadata.get_reloc_items(obj).unwrap_or(&[]).iter() {
+                let offset = item.src_offset as usize;
+                let target = match &self.metadata.objects[item.dst_object as usize] {
+                    ObjectKind::Function(target) | ObjectKind::BasicBlock(target) | ObjectKind::Data(target) => {
+                        self.get_staged_address(target.index as usize) + item.dst_offset as usize
+                    },
+                    _ => continue,
+                };
+                let kind = if offset == obj.get_size() { RelocKind::B_T4 } else { item.kind };
+                let code = if kind.size() == 2 { cb.read16(offset).unwrap() as u32 } else { cb.read32(offset).unwrap() };
+                let mut landing = decoder::decode(kind, code, cb.address + offset);
+
+                if self.sandbox.is_staged_trampoline(landing) {
+                    let trampoline = CodeBlock::new(landing, unsafe { self.platform.memory(landing, adjustment::TRAMPOLINE_SIZE) });
+                    let words = [trampoline.read32(0).unwrap(), trampoline.read32(4).unwrap()];
+                    landing = decoder::decode_trampoline(words).unwrap_or(landing);
+                }
+
+                // MOVW and MOVT only hold half of the address
+                let expected = match kind {
+                    RelocKind::MOVW => target & 0xffff,
+                    RelocKind::MOVT => target & 0xffff_0000,
+                    _ => target,
+                };
+                if landing != expected {
+                    panic!("relocated {:?} at offset {} of object {} lands on 0x{:x} instead of 0x{:x}",
+                           kind.resolve(code), offset, obj.index, landing, expected);
+                }
+            }
+        }
+    }
+
+    /// Switch the non-secure world to the layout being built at once
+    pub fn commit(&mut self) {
+        let Randomizer { platform, sandbox, dispatch_tbl, staging_tbl, .. } = self;
+
+        P::critical_section(|| {
+            dispatch_tbl.copy_from_slice(staging_tbl);
+            platform.set_vtor(staging_tbl[0] as usize);
+            sandbox.commit();
+            // only the new layout may run, the old one is built over next
+            platform.protect_sandbox(sandbox.banks(), Some(sandbox.active()), sandbox.data_size());
+        });
+    }
+
+    /// Switch the non-secure world to the original layout in the flash, to run it without randomization
+    ///
+    /// The sandbox is left alone, so `lookup()` keeps reporting the last committed layout,
+    /// but the non-secure world can no longer execute it. It executes the flash range
+    /// spanning the original objects instead.
+    pub fn commit_original(&mut self) -> Result<(), HarmError> {
+        let Randomizer { platform, sandbox, metadata, dispatch_tbl, .. } = self;
+        let vector_tbl = match metadata.objects.first() {
+            Some(ObjectKind::VectorTable(vector_tbl)) => vector_tbl.address,
+            _ => return Err(HarmError::MissingVectorTable),
+        };
+        let start = metadata.objects.iter().map(|object| object.get_object().get_address()).min().unwrap_or(vector_tbl);
+        let end = metadata.objects.iter().map(|object| object.get_object().get_address() + object.get_object().get_size()).max().unwrap_or(vector_tbl);
+
+        P::critical_section(|| {
+            for (entry, object) in dispatch_tbl.iter_mut().zip(metadata.objects.iter()) {
+                *entry = object.get_object().get_address() as u32;
+            }
+            platform.set_vtor(vector_tbl);
+            platform.protect_sandbox(sandbox.banks(), None, sandbox.data_size());
+            platform.execute_original((start, end - start));
+        });
+        Ok(())
+    }
+
+    /// Build a new layout and switch to it, the running layout is kept on failure
+    pub fn randomize(&mut self) -> Result<(), HarmError> {
+        self.shuffle()?;
+        self.ref_adjust()?;
+        self.commit();
+        Ok(())
+    }
+
+    /// Find the object of the running layout containing `addr`, returns it along with the offset of `addr`
+    pub fn lookup(&self, addr: usize) -> Option<(&'a ObjectKind, usize)> {
+        self.sandbox.lookup(addr)
+    }
+}
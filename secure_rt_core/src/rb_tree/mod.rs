@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod rb_tree;
+mod rb_node;
+
+pub(crate) use rb_node::RBNode;
\ No newline at end of file
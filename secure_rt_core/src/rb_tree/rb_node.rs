@@ -0,0 +1,37 @@
+use core::marker::Copy;
+
+/// Index of a node in the arena of a tree
+pub type NodeId = usize;
+
+/// Index of the (absent) leaf node
+pub const NIL: NodeId = usize::MAX;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Color {
+    Red,
+    Black,
+}
+
+pub struct RBNode<K, V> {
+    pub parent: NodeId,
+    pub l_child: NodeId,
+    pub r_child: NodeId,
+    pub data: V,
+    pub key: K,
+    pub color: Color,
+}
+
+impl<K, V> RBNode<K, V>
+        where K: PartialOrd + Copy, V: Clone {
+    /// A new node is always red and detached
+    pub fn new(key: K, data: V) -> Self {
+        Self {
+            parent: NIL,
+            l_child: NIL,
+            r_child: NIL,
+            color: Color::Red,
+            data,
+            key,
+        }
+    }
+}
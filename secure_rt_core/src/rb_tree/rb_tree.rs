@@ -0,0 +1,544 @@
+extern crate alloc;
+
+use super::rb_node::*;
+use alloc::vec::Vec;
+use core::marker::Copy;
+
+/// Red-Black Tree
+///
+/// Nodes live in an arena and refer to each other by index. Removed nodes are
+/// recycled through a free list and `clear()` keeps the arena's capacity, so
+/// a tree can be rebuilt over and over without touching the allocator.
+pub struct RBTree<K, V> {
+    /// arena of all nodes (including the free ones)
+    nodes: Vec<RBNode<K, V>>,
+
+    /// root of the tree
+    root: NodeId,
+
+    /// head of the free list, chained through `r_child`
+    free: NodeId,
+
+    /// number of entries
+    len: usize,
+}
+
+/// In-order iterator over the entries of a tree
+pub struct Iter<'a, K, V> {
+    tree: &'a RBTree<K, V>,
+    next: NodeId,
+}
+
+impl<K, V> RBTree<K, V>
+        where K: PartialOrd + Copy, V: Clone {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Create a tree that holds up to `capacity` entries without allocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(capacity),
+            root: NIL,
+            free: NIL,
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all entries, the memory of the arena is kept for reuse
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.root = NIL;
+        self.free = NIL;
+        self.len = 0;
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match self.node_search(key) {
+            NIL => None,
+            this => Some(&self.nodes[this].data),
+        }
+    }
+
+    /// Insert an entry, returns the previous data if `key` already exists
+    pub fn put(&mut self, key: K, data: V) -> Option<V> {
+        let mut parent = NIL;
+        let mut this = self.root;
+
+        while this != NIL {
+            parent = this;
+            let node = &mut self.nodes[this];
+            if key < node.key {
+                this = node.l_child;
+            } else if key > node.key {
+                this = node.r_child;
+            } else {
+                // key already exists
+                return Some(core::mem::replace(&mut node.data, data));
+            }
+        }
+
+        let this = self.alloc_node(key, data);
+        self.nodes[this].parent = parent;
+        if parent == NIL {
+            self.root = this;
+        } else if key < self.nodes[parent].key {
+            self.nodes[parent].l_child = this;
+        } else {
+            self.nodes[parent].r_child = this;
+        }
+
+        self.len += 1;
+        self.insert_fixup(this);
+        None
+    }
+
+    /// Remove an entry, returns its data if `key` exists
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let this = self.node_search(key);
+        if this == NIL {
+            return None;
+        }
+
+        let mut removed_color = self.color(this);
+        let child;
+        let child_parent;
+
+        if self.left(this) == NIL {
+            child = self.right(this);
+            child_parent = self.parent(this);
+            self.transplant(this, child);
+        } else if self.right(this) == NIL {
+            child = self.left(this);
+            child_parent = self.parent(this);
+            self.transplant(this, child);
+        } else {
+            // replace the node with its successor
+            let successor = self.minimum(self.right(this));
+            removed_color = self.color(successor);
+            child = self.right(successor);
+
+            if self.parent(successor) == this {
+                child_parent = successor;
+            } else {
+                child_parent = self.parent(successor);
+                self.transplant(successor, child);
+                let r_child = self.right(this);
+                self.nodes[successor].r_child = r_child;
+                self.nodes[r_child].parent = successor;
+            }
+
+            self.transplant(this, successor);
+            let l_child = self.left(this);
+            self.nodes[successor].l_child = l_child;
+            self.nodes[l_child].parent = successor;
+            self.nodes[successor].color = self.color(this);
+        }
+
+        if removed_color == Color::Black {
+            self.remove_fixup(child, child_parent);
+        }
+
+        self.len -= 1;
+        let data = self.nodes[this].data.clone();
+        self.free_node(this);
+        Some(data)
+    }
+
+    /// Find the entry with the greatest key less than or equal to `key`
+    pub fn floor(&self, key: &K) -> Option<(K, V)> {
+        let mut this = self.root;
+        let mut candidate = NIL;
+
+        while this != NIL {
+            let node = &self.nodes[this];
+            if node.key == *key {
+                candidate = this;
+                break;
+            } else if node.key < *key {
+                candidate = this;
+                this = node.r_child;
+            } else {
+                this = node.l_child;
+            }
+        }
+
+        self.entry(candidate)
+    }
+
+    /// Find the entry with the least key greater than or equal to `key`
+    pub fn ceiling(&self, key: &K) -> Option<(K, V)> {
+        let mut this = self.root;
+        let mut candidate = NIL;
+
+        while this != NIL {
+            let node = &self.nodes[this];
+            if node.key == *key {
+                candidate = this;
+                break;
+            } else if node.key > *key {
+                candidate = this;
+                this = node.l_child;
+            } else {
+                this = node.r_child;
+            }
+        }
+
+        self.entry(candidate)
+    }
+
+    /// Iterate over all entries in ascending key order
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            tree: self,
+            next: if self.root == NIL { NIL } else { self.minimum(self.root) },
+        }
+    }
+
+    /// Verify the Red-Black properties, the links and the ordering of the tree
+    pub fn check_invariants(&self) -> Result<(), &'static str> {
+        if self.color(self.root) != Color::Black {
+            return Err("root is red");
+        }
+        if self.root != NIL && self.parent(self.root) != NIL {
+            return Err("root has a parent");
+        }
+
+        let mut count = 0;
+        self.check_subtree(self.root, &mut count)?;
+        if count != self.len {
+            return Err("length does not match the number of nodes");
+        }
+
+        let mut prev: Option<K> = None;
+        for (key, _) in self.iter() {
+            if let Some(prev) = prev {
+                if prev >= *key {
+                    return Err("keys are out of order");
+                }
+            }
+            prev = Some(*key);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the black height of the subtree
+    fn check_subtree(&self, this: NodeId, count: &mut usize) -> Result<usize, &'static str> {
+        if this == NIL {
+            return Ok(1);
+        }
+
+        *count += 1;
+        if *count > self.len {
+            return Err("tree contains a cycle");
+        }
+
+        let node = &self.nodes[this];
+        for child in [node.l_child, node.r_child].iter() {
+            if *child != NIL && self.parent(*child) != this {
+                return Err("broken parent link");
+            }
+        }
+
+        if node.color == Color::Red
+                && (self.color(node.l_child) == Color::Red || self.color(node.r_child) == Color::Red) {
+            return Err("red node has a red child");
+        }
+
+        let l_height = self.check_subtree(node.l_child, count)?;
+        let r_height = self.check_subtree(node.r_child, count)?;
+        if l_height != r_height {
+            return Err("black heights differ");
+        }
+
+        Ok(l_height + if node.color == Color::Black { 1 } else { 0 })
+    }
+
+    fn entry(&self, this: NodeId) -> Option<(K, V)> {
+        if this == NIL {
+            None
+        } else {
+            let node = &self.nodes[this];
+            Some((node.key, node.data.clone()))
+        }
+    }
+
+    fn alloc_node(&mut self, key: K, data: V) -> NodeId {
+        let node = RBNode::new(key, data);
+
+        if self.free != NIL {
+            let this = self.free;
+            self.free = self.nodes[this].r_child;
+            self.nodes[this] = node;
+            this
+        } else {
+            self.nodes.push(node);
+            self.nodes.len() - 1
+        }
+    }
+
+    fn free_node(&mut self, this: NodeId) {
+        let node = &mut self.nodes[this];
+        node.parent = NIL;
+        node.l_child = NIL;
+        node.r_child = self.free;
+        self.free = this;
+    }
+
+    #[inline]
+    fn color(&self, this: NodeId) -> Color {
+        if this == NIL { Color::Black } else { self.nodes[this].color }
+    }
+
+    #[inline]
+    fn set_color(&mut self, this: NodeId, color: Color) {
+        if this != NIL {
+            self.nodes[this].color = color;
+        }
+    }
+
+    #[inline]
+    fn parent(&self, this: NodeId) -> NodeId {
+        self.nodes[this].parent
+    }
+
+    #[inline]
+    fn left(&self, this: NodeId) -> NodeId {
+        self.nodes[this].l_child
+    }
+
+    #[inline]
+    fn right(&self, this: NodeId) -> NodeId {
+        self.nodes[this].r_child
+    }
+
+    fn minimum(&self, mut this: NodeId) -> NodeId {
+        while self.left(this) != NIL {
+            this = self.left(this);
+        }
+        this
+    }
+
+    fn successor(&self, mut this: NodeId) -> NodeId {
+        if self.right(this) != NIL {
+            return self.minimum(self.right(this));
+        }
+
+        let mut parent = self.parent(this);
+        while parent != NIL && this == self.right(parent) {
+            this = parent;
+            parent = self.parent(parent);
+        }
+        parent
+    }
+
+    fn node_search(&self, key: &K) -> NodeId {
+        let mut this = self.root;
+
+        while this != NIL {
+            let node = &self.nodes[this];
+            if *key < node.key {
+                this = node.l_child;
+            } else if *key > node.key {
+                this = node.r_child;
+            } else {
+                break;
+            }
+        }
+
+        this
+    }
+
+    /// Replace the subtree rooted at `this` with the one rooted at `other`
+    fn transplant(&mut self, this: NodeId, other: NodeId) {
+        let parent = self.parent(this);
+
+        if parent == NIL {
+            self.root = other;
+        } else if this == self.left(parent) {
+            self.nodes[parent].l_child = other;
+        } else {
+            self.nodes[parent].r_child = other;
+        }
+
+        if other != NIL {
+            self.nodes[other].parent = parent;
+        }
+    }
+
+    fn rotate_left(&mut self, this: NodeId) {
+        let r_child = self.right(this);
+        let rl_child = self.left(r_child);
+
+        self.nodes[this].r_child = rl_child;
+        if rl_child != NIL {
+            self.nodes[rl_child].parent = this;
+        }
+
+        self.transplant(this, r_child);
+        self.nodes[r_child].l_child = this;
+        self.nodes[this].parent = r_child;
+    }
+
+    fn rotate_right(&mut self, this: NodeId) {
+        let l_child = self.left(this);
+        let lr_child = self.right(l_child);
+
+        self.nodes[this].l_child = lr_child;
+        if lr_child != NIL {
+            self.nodes[lr_child].parent = this;
+        }
+
+        self.transplant(this, l_child);
+        self.nodes[l_child].r_child = this;
+        self.nodes[this].parent = l_child;
+    }
+
+    fn insert_fixup(&mut self, mut this: NodeId) {
+        // a red parent is never the root, so the grandparent always exists
+        while self.color(self.parent(this)) == Color::Red {
+            let parent = self.parent(this);
+            let grandparent = self.parent(parent);
+
+            if parent == self.left(grandparent) {
+                let uncle = self.right(grandparent);
+                if self.color(uncle) == Color::Red {
+                    self.set_color(parent, Color::Black);
+                    self.set_color(uncle, Color::Black);
+                    self.set_color(grandparent, Color::Red);
+                    this = grandparent;
+                } else {
+                    if this == self.right(parent) {
+                        this = parent;
+                        self.rotate_left(this);
+                    }
+                    let parent = self.parent(this);
+                    let grandparent = self.parent(parent);
+                    self.set_color(parent, Color::Black);
+                    self.set_color(grandparent, Color::Red);
+                    self.rotate_right(grandparent);
+                }
+            } else {
+                let uncle = self.left(grandparent);
+                if self.color(uncle) == Color::Red {
+                    self.set_color(parent, Color::Black);
+                    self.set_color(uncle, Color::Black);
+                    self.set_color(grandparent, Color::Red);
+                    this = grandparent;
+                } else {
+                    if this == self.left(parent) {
+                        this = parent;
+                        self.rotate_right(this);
+                    }
+                    let parent = self.parent(this);
+                    let grandparent = self.parent(parent);
+                    self.set_color(parent, Color::Black);
+                    self.set_color(grandparent, Color::Red);
+                    self.rotate_left(grandparent);
+                }
+            }
+        }
+
+        let root = self.root;
+        self.set_color(root, Color::Black);
+    }
+
+    /// `this` carries an extra black, `parent` is passed since `this` may be NIL
+    fn remove_fixup(&mut self, mut this: NodeId, mut parent: NodeId) {
+        while this != self.root && self.color(this) == Color::Black {
+            if this == self.left(parent) {
+                let mut sibling = self.right(parent);
+                if self.color(sibling) == Color::Red {
+                    self.set_color(sibling, Color::Black);
+                    self.set_color(parent, Color::Red);
+                    self.rotate_left(parent);
+                    sibling = self.right(parent);
+                }
+
+                if self.color(self.left(sibling)) == Color::Black && self.color(self.right(sibling)) == Color::Black {
+                    self.set_color(sibling, Color::Red);
+                    this = parent;
+                    parent = self.parent(this);
+                } else {
+                    if self.color(self.right(sibling)) == Color::Black {
+                        let sl_child = self.left(sibling);
+                        self.set_color(sl_child, Color::Black);
+                        self.set_color(sibling, Color::Red);
+                        self.rotate_right(sibling);
+                        sibling = self.right(parent);
+                    }
+                    let sr_child = self.right(sibling);
+                    self.set_color(sibling, self.color(parent));
+                    self.set_color(parent, Color::Black);
+                    self.set_color(sr_child, Color::Black);
+                    self.rotate_left(parent);
+                    this = self.root;
+                }
+            } else {
+                let mut sibling = self.left(parent);
+                if self.color(sibling) == Color::Red {
+                    self.set_color(sibling, Color::Black);
+                    self.set_color(parent, Color::Red);
+                    self.rotate_right(parent);
+                    sibling = self.left(parent);
+                }
+
+                if self.color(self.left(sibling)) == Color::Black && self.color(self.right(sibling)) == Color::Black {
+                    self.set_color(sibling, Color::Red);
+                    this = parent;
+                    parent = self.parent(this);
+                } else {
+                    if self.color(self.left(sibling)) == Color::Black {
+                        let sr_child = self.right(sibling);
+                        self.set_color(sr_child, Color::Black);
+                        self.set_color(sibling, Color::Red);
+                        self.rotate_left(sibling);
+                        sibling = self.left(parent);
+                    }
+                    let sl_child = self.left(sibling);
+                    self.set_color(sibling, self.color(parent));
+                    self.set_color(parent, Color::Black);
+                    self.set_color(sl_child, Color::Black);
+                    self.rotate_right(parent);
+                    this = self.root;
+                }
+            }
+        }
+
+        self.set_color(this, Color::Black);
+    }
+}
+
+impl<K, V> Default for RBTree<K, V>
+        where K: PartialOrd + Copy, V: Clone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+        where K: PartialOrd + Copy, V: Clone {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == NIL {
+            return None;
+        }
+
+        let this = self.next;
+        self.next = self.tree.successor(this);
+        let node = &self.tree.nodes[this];
+        Some((&node.key, &node.data))
+    }
+}
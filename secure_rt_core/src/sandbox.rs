@@ -0,0 +1,333 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use super::adjustment::{encode_trampoline, TRAMPOLINE_SIZE};
+use super::error::{HarmError, TRAMPOLINE_OBJECT};
+use super::objects::{Object, ObjectKind};
+
+use super::rb_tree::rb_tree::RBTree;
+use super::rb_tree::RBNode;
+
+/// Number of memory banks managed by a sandbox
+pub const NUM_OF_BANKS: usize = 2;
+
+/// Granularity of the SAU and MPU regions, base and size of every bank are multiples of it
+pub const REGION_ALIGN: usize = 32;
+
+/// Thumb instruction filling the space between objects
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trap {
+    /// `UDF #0`, raises a UsageFault
+    Udf,
+    /// `BKPT #0`, halts under a debugger and escalates to a HardFault otherwise
+    Bkpt,
+}
+
+impl Trap {
+    #[inline]
+    pub fn encoding(self) -> u16 {
+        match self {
+            Trap::Udf => 0xde00,
+            Trap::Bkpt => 0xbe00,
+        }
+    }
+}
+
+/// Randomization policy of the space between objects
+#[derive(Clone, Copy, Debug)]
+pub struct PaddingPolicy {
+    /// Largest random gap inserted before an object in bytes, 0 packs objects back-to-back
+    ///
+    /// Gaps are further bounded so that all objects still fit in a bank.
+    pub max_gap: usize,
+    /// Instruction filling gaps, alignment padding and the unused end of a bank
+    pub trap: Trap,
+}
+
+impl PaddingPolicy {
+    /// No random gaps, alignment padding is still filled with `UDF`
+    pub const NONE: PaddingPolicy = PaddingPolicy { max_gap: 0, trap: Trap::Udf };
+}
+
+/// What the non-secure world may do with the code in the sandbox
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CodeProtection {
+    /// The code may read the literal pools embedded in it, far branches may go through trampolines
+    Readable,
+    /// The code holds no data it loads, so that it can run from execute-only memory
+    ///
+    /// Literal pools must be `Data` objects in the data area of the bank, and
+    /// out-of-range branches are errors since trampolines hold their target as
+    /// a literal.
+    ExecuteOnly,
+}
+
+/// Alignment of `object` in the sandbox, as a power of two
+pub(crate) fn get_align_bits(object: &ObjectKind) -> u8 {
+    match object {
+        ObjectKind::VectorTable(_) => 7,
+        // doublewords of a literal pool are loaded with `LDRD`
+        ObjectKind::Data(_) => 3,
+        ObjectKind::Function(obj) | ObjectKind::BasicBlock(obj) => {
+            if obj.get_address() & 3 == 0 { 2 } else { 1 }
+        },
+    }
+}
+
+/// Check that the `(base, size)` banks can hold the sandbox
+///
+/// Banks must be aligned to `REGION_ALIGN`, must not overlap each other and
+/// must each lie within one of the `(base, size)` regions in `non_secure`,
+/// the memory the SAU actually grants to the non-secure world.
+pub fn check_banks(banks: &[(usize, usize)], non_secure: &[(usize, usize)]) -> Result<(), HarmError> {
+    for (i, &(base, size)) in banks.iter().enumerate() {
+        let invalid = |reason| Err(HarmError::InvalidSandbox { base, size, reason });
+
+        if size == 0 {
+            return invalid("empty bank");
+        }
+        if base % REGION_ALIGN != 0 || size % REGION_ALIGN != 0 {
+            return invalid("not aligned to the SAU and MPU granularity");
+        }
+        if base.checked_add(size).is_none() {
+            return invalid("past the end of the address space");
+        }
+        if banks[.. i].iter().any(|&(other, other_size)| base < other + other_size && other < base + size) {
+            return invalid("overlaps another bank");
+        }
+        if !non_secure.iter().any(|&(region, region_size)| base >= region && base - region <= region_size && size <= region_size - (base - region)) {
+            return invalid("not within a non-secure region");
+        }
+    }
+    Ok(())
+}
+
+/// Fill `memory` with `trap` instructions
+fn fill_trap(memory: &mut [u8], trap: Trap) {
+    let encoding = trap.encoding().to_le_bytes();
+
+    for halfword in memory.chunks_mut(2) {
+        let len = halfword.len();
+        halfword.copy_from_slice(&encoding[.. len]);
+    }
+}
+
+/// Sandbox Struct
+///
+/// A new layout is always built in a bank that is not running, so the
+/// non-secure world keeps executing the active bank until `commit()`.
+pub struct SandBox<'a> {
+    /// memory banks of the sandbox and their base addresses
+    banks: [(usize, &'a mut[u8]); NUM_OF_BANKS],
+
+    /// bank holding the live layout
+    active: usize,
+
+    /// bank the next layout is built in
+    staging: usize,
+
+    /// pointer of next availiable address
+    next_ptr: usize,
+
+    /// capacity of the sandbox
+    capacity: usize,
+
+    /// size of the data area at the end of each bank, holding the `Data` objects
+    data_size: usize,
+
+    /// pointer of next availiable address in the data area
+    next_data: usize,
+
+    /// bytes left in the data area
+    data_capacity: usize,
+
+    /// instruction filling the space between objects
+    trap: Trap,
+
+    /// Red-Black Trees that index all objects of each bank by address, along with their instance size
+    index: [RBTree<usize, (&'a ObjectKind, usize)>; NUM_OF_BANKS],
+
+    /// `(address, target)` of the long-branch trampolines of each bank
+    trampolines: [Vec<(usize, usize)>; NUM_OF_BANKS],
+}
+
+
+impl<'a> SandBox<'a> {
+    /// Create a sandbox from `(base, memory)` banks, able to index `num_of_objects` objects
+    pub fn new(banks: [(usize, &'a mut [u8]); NUM_OF_BANKS], num_of_objects: usize) -> Self {
+        let mut sandbox = SandBox {
+            banks,
+            active: NUM_OF_BANKS - 1,
+            staging: 0,
+            next_ptr: 0,
+            capacity: 0,
+            data_size: 0,
+            next_data: 0,
+            data_capacity: 0,
+            trap: PaddingPolicy::NONE.trap,
+            index: [(); NUM_OF_BANKS].map(|_| RBTree::<usize, (&'a ObjectKind, usize)>::with_capacity(num_of_objects)),
+            trampolines: [(); NUM_OF_BANKS].map(|_| Vec::new()),
+        };
+        sandbox.reset();
+        sandbox
+    }
+
+    /// Bytes of heap the address index of all banks takes for `num_of_objects` objects
+    pub const fn index_size(num_of_objects: usize) -> usize {
+        NUM_OF_BANKS * num_of_objects * size_of::<RBNode<usize, (&ObjectKind, usize)>>()
+    }
+
+    fn get_base(&self) -> usize {
+        self.banks[self.staging].0
+    }
+
+    /// Allocate `block_size` bytes for object `index` after a gap of at least `gap` bytes, in the data area for `data`
+    fn get_block(&mut self, index: u16, block_size: usize, align_bits: u8, gap: usize, data: bool) -> Result<(usize, &mut [u8]), HarmError> {
+        let base = self.get_base();
+        let (next_ptr, capacity) = if data {
+            (&mut self.next_data, &mut self.data_capacity)
+        } else {
+            (&mut self.next_ptr, &mut self.capacity)
+        };
+        let align_bytes: usize = 1 << align_bits;
+        let block_base: usize = (*next_ptr + gap + (align_bytes - 1)) & !(align_bytes - 1);
+        let actual_size = block_base - *next_ptr + block_size;
+        let padding_i = *next_ptr - base;
+        let offset_i = block_base - base;
+
+        // allocate a block from the sandbox
+        if *capacity >= actual_size {
+            *capacity -= actual_size;
+            *next_ptr += actual_size;
+            let bank = &mut self.banks[self.staging].1;
+            fill_trap(&mut bank[padding_i .. offset_i], self.trap);
+            Ok((block_base, &mut bank[offset_i .. offset_i + block_size]))
+        } else {
+            Err(HarmError::SandboxExhausted { object: index, needed: actual_size, left: *capacity })
+        }
+    }
+
+    /// Bytes left for code in the staging bank
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Keep the last `size` bytes of every bank for `Data` objects, applies from the next `reset()`
+    ///
+    /// `size` must be a multiple of `REGION_ALIGN`, so that the code and the
+    /// data of a bank can be protected apart.
+    #[inline]
+    pub fn set_data_size(&mut self, size: usize) {
+        self.data_size = size;
+    }
+
+    /// Size of the data area at the end of every bank
+    #[inline]
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    /// Choose the instruction filling the space between objects
+    #[inline]
+    pub fn set_trap(&mut self, trap: Trap) {
+        self.trap = trap;
+    }
+
+    /// Place `object` in the staging bank after a gap of at least `gap` bytes, `code` is its original content
+    ///
+    /// `size` bytes are reserved, the ones following `code` are left for the caller to fill.
+    /// `Data` objects go to the data area, without a gap.
+    pub fn push(&mut self, object: &'a ObjectKind, code: &[u8], size: usize, gap: usize) -> Result<usize, HarmError> {
+        let obj: (&Object, u8) = (object.get_object(), get_align_bits(object));
+
+        // copy the object code to the sandbox
+
+        let gap = if object.is_data() { 0 } else { gap };
+        let (address, block) = self.get_block(obj.0.index, size, obj.1, gap, object.is_data())?;
+        block[.. code.len()].copy_from_slice(code);
+        self.index[self.staging].put(address, (object, size));
+        Ok(address)
+    }
+
+    /// Place a trampoline to `target` in the staging bank, after the objects
+    pub fn push_trampoline(&mut self, target: usize) -> Result<usize, HarmError> {
+        let (address, block) = self.get_block(TRAMPOLINE_OBJECT, TRAMPOLINE_SIZE, 2, 0, false)?;
+        let code = encode_trampoline(target);
+
+        block[.. 4].copy_from_slice(&code[0].to_le_bytes());
+        block[4 ..].copy_from_slice(&code[1].to_le_bytes());
+        self.trampolines[self.staging].push((address, target));
+        Ok(address)
+    }
+
+    /// Trampolines to `target` in the staging bank
+    pub fn find_trampolines(&self, target: usize) -> impl Iterator<Item = usize> + '_ {
+        self.trampolines[self.staging].iter().filter(move |t| t.1 == target).map(|t| t.0)
+    }
+
+    /// Whether a trampoline of the staging bank starts at `addr`
+    pub fn is_staged_trampoline(&self, addr: usize) -> bool {
+        self.trampolines[self.staging].iter().any(|t| t.0 == addr)
+    }
+
+    /// Target of the live trampoline whose `LDR` is at `addr`
+    pub fn get_trampoline_target(&self, addr: usize) -> Option<usize> {
+        self.trampolines[self.active].iter().find(|t| t.0 == addr).map(|t| t.1)
+    }
+
+    /// Start building a new layout in the bank following the active one
+    #[inline]
+    pub fn reset(&mut self) {
+        self.staging = (self.active + 1) % NUM_OF_BANKS;
+        self.next_ptr = self.get_base();
+        self.capacity = self.banks[self.staging].1.len().saturating_sub(self.data_size);
+        self.next_data = self.next_ptr + self.capacity;
+        self.data_capacity = self.data_size;
+        self.index[self.staging].clear();
+        self.trampolines[self.staging].clear();
+    }
+
+    /// Fill the unused ends of the code and the data of the staging bank with traps
+    ///
+    /// Trampolines may still be placed afterwards, they overwrite the traps.
+    pub fn finish(&mut self) {
+        let base = self.get_base();
+        let bank = &mut self.banks[self.staging].1;
+        let data = bank.len() - self.data_size;
+        fill_trap(&mut bank[self.next_ptr - base .. data], self.trap);
+        fill_trap(&mut bank[self.next_data - base ..], self.trap);
+    }
+
+    /// Find the object of the live layout containing `addr`, returns it along with the offset of `addr`
+    pub fn lookup(&self, addr: usize) -> Option<(&'a ObjectKind, usize)> {
+        let (base, (object, size)) = self.index[self.active].floor(&addr)?;
+
+        if addr < base + size {
+            Some((object, addr - base))
+        } else {
+            None
+        }
+    }
+
+    /// Make the staging bank the active one
+    #[inline]
+    pub fn commit(&mut self) {
+        self.active = self.staging;
+    }
+
+    /// `(base, size)` of every bank
+    pub fn banks(&self) -> [(usize, usize); NUM_OF_BANKS] {
+        let mut banks = [(0, 0); NUM_OF_BANKS];
+        for (bank, (base, memory)) in banks.iter_mut().zip(self.banks.iter()) {
+            *bank = (*base, memory.len());
+        }
+        banks
+    }
+
+    /// Bank holding the live layout
+    #[inline]
+    pub fn active(&self) -> usize {
+        self.active
+    }
+}
@@ -0,0 +1,175 @@
+//! Random placement of the initial non-secure stacks and heap
+//!
+//! The vector table of the firmware fixes the top of its main stack, which
+//! would then be the same at every boot. Instead, the secure runtime draws the
+//! stacks from a window of the non-secure RAM nothing else uses, and guards
+//! their bottom with the stack limit registers of the non-secure world. The
+//! heap start can be drawn as well, for firmware reading it from a variable.
+
+use super::error::HarmError;
+use super::random::RandomSource;
+
+/// Alignment of the stack pointers and their limits, required by the AAPCS and by `MSPLIM`/`PSPLIM`
+pub const STACK_ALIGN: usize = 8;
+
+/// Where the initial non-secure stacks may be placed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StackPolicy {
+    /// `(base, size)` of the non-secure RAM the stacks are drawn from
+    pub window: (usize, usize),
+    /// Size of the main stack
+    pub main_size: usize,
+    /// Size of the process stack, 0 leaves `PSP_NS` and `PSPLIM_NS` to the firmware
+    pub process_size: usize,
+    /// Where the heap start is drawn from, if the firmware takes it from a variable
+    pub heap: Option<HeapPolicy>,
+}
+
+/// Where the non-secure heap may start
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeapPolicy {
+    /// Address of the word the firmware reads its heap start from
+    ///
+    /// It must be left alone by the startup code of the firmware (e.g. in
+    /// `.noinit`), since it is written before the firmware runs.
+    pub symbol: usize,
+    /// `(base, size)` of the range the heap start is drawn from
+    pub window: (usize, usize),
+}
+
+/// Initial stacks and heap of the non-secure world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StackLayout {
+    /// Initial `MSP_NS`, the top of the main stack
+    pub msp: usize,
+    /// `MSPLIM_NS`, the bottom of the main stack
+    pub msplim: usize,
+    /// Initial `PSP_NS`, 0 when the firmware sets it
+    pub psp: usize,
+    /// `PSPLIM_NS`, 0 when the firmware sets it
+    pub psplim: usize,
+    /// Address of the heap start variable and the heap start written to it
+    pub heap: Option<(usize, usize)>,
+}
+
+/// Whether two `(base, size)` ranges share at least one byte
+fn overlaps(a: (usize, usize), b: (usize, usize)) -> bool {
+    a.0 < b.0.saturating_add(b.1) && b.0 < a.0.saturating_add(a.1)
+}
+
+/// Whether the `(base, size)` range lies within one of the `(base, size)` regions
+fn within(range: (usize, usize), regions: &[(usize, usize)]) -> bool {
+    let (base, size) = range;
+    regions.iter().any(|&(region, region_size)| base >= region && base - region <= region_size && size <= region_size - (base - region))
+}
+
+/// Random multiple of `STACK_ALIGN` from 0 up to `slack`
+fn draw<R: RandomSource>(rng: &mut R, slack: usize) -> usize {
+    rng.next_below((slack / STACK_ALIGN + 1) as u32) as usize * STACK_ALIGN
+}
+
+impl StackLayout {
+    /// Main stack of the vector table, without a limit
+    pub const fn fixed(msp: usize) -> Self {
+        StackLayout { msp, msplim: 0, psp: 0, psplim: 0, heap: None }
+    }
+
+    /// Top of the stack `sp` points into, the main stack unless `sp` is in the process stack
+    pub fn stack_top(&self, sp: usize) -> usize {
+        if self.psp != 0 && sp >= self.psplim && sp <= self.psp {
+            self.psp
+        } else {
+            self.msp
+        }
+    }
+}
+
+impl StackPolicy {
+    /// Check that the windows can be used by the non-secure world
+    ///
+    /// The windows must lie in the `(base, size)` regions of `non_secure`, stay
+    /// clear of the sandbox `banks` and of each other, and the stack window must
+    /// hold both stacks. The heap start variable must be outside of the windows.
+    pub fn check(&self, non_secure: &[(usize, usize)], banks: &[(usize, usize)]) -> Result<(), HarmError> {
+        let (base, size) = self.window;
+        let invalid = |reason| Err(HarmError::InvalidStackWindow { base, size, reason });
+
+        check_window(self.window, non_secure, banks)?;
+        if self.main_size == 0 || self.main_size % STACK_ALIGN != 0 || self.process_size % STACK_ALIGN != 0 {
+            return invalid("stack size not a non-empty multiple of 8 bytes");
+        }
+        if self.main_size.saturating_add(self.process_size) > size {
+            return invalid("too small for the stacks");
+        }
+
+        if let Some(heap) = self.heap {
+            check_window(heap.window, non_secure, banks)?;
+            if overlaps(heap.window, self.window) {
+                let (base, size) = heap.window;
+                return Err(HarmError::InvalidStackWindow { base, size, reason: "overlaps the stacks" });
+            }
+
+            let symbol = (heap.symbol, 4);
+            let invalid = |reason| Err(HarmError::InvalidStackWindow { base: symbol.0, size: symbol.1, reason });
+            if symbol.0 % 4 != 0 {
+                return invalid("heap start variable not aligned");
+            }
+            if !within(symbol, non_secure) {
+                return invalid("not within a non-secure region");
+            }
+            if overlaps(symbol, self.window) || overlaps(symbol, heap.window) || banks.iter().any(|&bank| overlaps(symbol, bank)) {
+                return invalid("heap start variable inside a window or the sandbox");
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the stacks and the heap start
+    ///
+    /// The stacks come in a random order, each after a random gap, and the heap
+    /// starts anywhere in its window. Every address is a multiple of `STACK_ALIGN`.
+    /// The policy must have passed `check()`.
+    pub fn place<R: RandomSource>(&self, rng: &mut R) -> StackLayout {
+        let (base, size) = self.window;
+        let slack = size - self.main_size - self.process_size;
+
+        let first_gap = draw(rng, slack);
+        let second_gap = draw(rng, slack - first_gap);
+        let main_first = self.process_size == 0 || rng.next_below(2) == 0;
+        let (first, second) = if main_first { (self.main_size, self.process_size) } else { (self.process_size, self.main_size) };
+
+        // bottom of each stack
+        let first_base = base + first_gap;
+        let second_base = first_base + first + second_gap;
+        let ((msplim, main), (psplim, process)) = if main_first {
+            ((first_base, first), (second_base, second))
+        } else {
+            ((second_base, second), (first_base, first))
+        };
+
+        let (psp, psplim) = if process == 0 { (0, 0) } else { (psplim + process, psplim) };
+        let heap = self.heap.map(|heap| (heap.symbol, heap.window.0 + draw(rng, heap.window.1 - STACK_ALIGN)));
+
+        StackLayout { msp: msplim + main, msplim, psp, psplim, heap }
+    }
+}
+
+/// Check that the `(base, size)` window is aligned, non-secure and out of the sandbox `banks`
+fn check_window(window: (usize, usize), non_secure: &[(usize, usize)], banks: &[(usize, usize)]) -> Result<(), HarmError> {
+    let (base, size) = window;
+    let invalid = |reason| Err(HarmError::InvalidStackWindow { base, size, reason });
+
+    if size == 0 || base % STACK_ALIGN != 0 || size % STACK_ALIGN != 0 {
+        return invalid("not a non-empty multiple of 8 bytes");
+    }
+    if base.checked_add(size).is_none() {
+        return invalid("past the end of the address space");
+    }
+    if !within(window, non_secure) {
+        return invalid("not within a non-secure region");
+    }
+    if banks.iter().any(|&bank| overlaps(window, bank)) {
+        return invalid("overlaps the sandbox");
+    }
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! TrustZone configuration built from a description of the memory map
+//!
+//! The SAU regions and the rules of the memory protection checkers are
+//! derived from where the secure runtime, its non-secure callable veneers
+//! and the sandbox live, instead of being written out by hand. Every value
+//! is kept so that the registers can be read back and compared after boot.
+//! Nothing here allocates, the configuration is applied before the heap exists.
+
+use super::error::HarmError;
+use super::sandbox::{check_banks, REGION_ALIGN};
+
+/// Number of regions of the SAU
+pub const SAU_REGIONS: usize = 8;
+
+/// Security level of a sector of a memory protection checker, secure and privileged access only
+pub const SECURE_PRIV: u32 = 0x3;
+
+/// Security level of a sector of a memory protection checker, open to the non-secure world
+pub const NS_USER: u32 = 0x0;
+
+/// Security attribution of an address range by the SAU
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attribution {
+    /// Reachable from the secure world only, the attribution of addresses no enabled region covers
+    Secure,
+    /// Reachable from the non-secure world
+    NonSecure,
+    /// Secure, but the non-secure world may branch to the `SG` instructions of the veneers in it
+    NonSecureCallable,
+}
+
+/// Region of the SAU, `size` bytes at `base`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub base: usize,
+    pub size: usize,
+    pub attribution: Attribution,
+}
+
+impl Region {
+    /// Region left disabled, its addresses stay secure
+    pub const DISABLED: Region = Region { base: 0, size: 0, attribution: Attribution::Secure };
+
+    /// Inclusive limit address, as programmed in `SAU_RLAR`
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.base + self.size - 1
+    }
+
+    /// Whether the region is programmed, i.e. its addresses are not simply secure
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.attribution != Attribution::Secure
+    }
+}
+
+/// Description of the memory map the TrustZone configuration is derived from
+///
+/// All ranges are `(base, size)` pairs.
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryMap<'a> {
+    /// Code and data of the secure runtime (`FLASH` and `RAM` of `memory.x`)
+    pub secure: &'a [(usize, usize)],
+    /// Non-secure callable veneers, the `.gnu.sgstubs` section (`FLASH_NSC` of `memory.x`)
+    pub veneers: (usize, usize),
+    /// Memory and peripherals left to the non-secure world
+    pub non_secure: &'a [(usize, usize)],
+    /// Sandbox banks (`sandbox.yaml`), must lie in `non_secure`
+    pub banks: &'a [(usize, usize)],
+}
+
+/// Whether two `(base, size)` ranges share at least one byte
+fn overlaps(a: (usize, usize), b: (usize, usize)) -> bool {
+    a.0 < b.0.saturating_add(b.1) && b.0 < a.0.saturating_add(a.1)
+}
+
+impl<'a> MemoryMap<'a> {
+    /// SAU regions of the memory map, the non-secure ranges in order followed by the veneers
+    ///
+    /// Unused regions are `Region::DISABLED`. Fails if a range cannot be
+    /// programmed in the SAU, if a non-secure range would expose the secure
+    /// runtime, or if a sandbox bank falls outside of the non-secure ranges.
+    pub fn sau_regions(&self) -> Result<[Region; SAU_REGIONS], HarmError> {
+        let mut regions = [Region::DISABLED; SAU_REGIONS];
+
+        if self.non_secure.len() + 1 > SAU_REGIONS {
+            let (base, size) = self.non_secure[SAU_REGIONS - 1];
+            return Err(HarmError::InvalidMemoryMap { base, size, reason: "too many regions for the SAU" });
+        }
+
+        for (i, &(base, size)) in self.non_secure.iter().enumerate() {
+            check_region(base, size)?;
+
+            let invalid = |reason| Err(HarmError::InvalidMemoryMap { base, size, reason });
+            if self.secure.iter().chain(core::iter::once(&self.veneers)).any(|&range| overlaps((base, size), range)) {
+                return invalid("overlaps the secure runtime");
+            }
+            if self.non_secure[.. i].iter().any(|&range| overlaps((base, size), range)) {
+                return invalid("overlaps another region");
+            }
+            regions[i] = Region { base, size, attribution: Attribution::NonSecure };
+        }
+
+        let (base, size) = self.veneers;
+        check_region(base, size)?;
+        regions[self.non_secure.len()] = Region { base, size, attribution: Attribution::NonSecureCallable };
+
+        check_banks(self.banks, self.non_secure)?;
+        Ok(regions)
+    }
+}
+
+/// Check that `size` bytes at `base` can be programmed as a region of the SAU
+fn check_region(base: usize, size: usize) -> Result<(), HarmError> {
+    let invalid = |reason| Err(HarmError::InvalidMemoryMap { base, size, reason });
+
+    if size == 0 {
+        return invalid("empty region");
+    }
+    if base % REGION_ALIGN != 0 || size % REGION_ALIGN != 0 {
+        return invalid("not aligned to the SAU granularity");
+    }
+    if base.checked_add(size).is_none() {
+        return invalid("past the end of the address space");
+    }
+    Ok(())
+}
+
+/// Compare the regions read back from the SAU with the expected ones
+///
+/// Only the attribution of a disabled region is compared, its addresses are meaningless.
+pub fn check_sau(expected: &[Region], actual: &[Region]) -> Result<(), HarmError> {
+    for (region, (expected, actual)) in expected.iter().zip(actual.iter()).enumerate() {
+        let same = expected.attribution == actual.attribution
+            && (!expected.is_enabled() || (expected.base, expected.size) == (actual.base, actual.size));
+        if !same {
+            return Err(HarmError::SauMismatch { region: region as u8 });
+        }
+    }
+    if expected.len() != actual.len() {
+        return Err(HarmError::SauMismatch { region: expected.len().min(actual.len()) as u8 });
+    }
+    Ok(())
+}
+
+/// Value of the register at `address`, or of the bits of it in `mask`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegisterRule {
+    /// Name of the register in the reference manual, for diagnostics
+    pub name: &'static str,
+    pub address: usize,
+    /// Bits written by the rule, the others keep their value
+    pub mask: u32,
+    pub value: u32,
+    /// Bits of `mask` that do not read back as written, such as the key of `AIRCR`
+    pub write_only: u32,
+}
+
+impl RegisterRule {
+    /// Rule setting the whole register
+    pub const fn new(name: &'static str, address: usize, value: u32) -> Self {
+        RegisterRule { name, address, mask: u32::MAX, value, write_only: 0 }
+    }
+
+    /// Rule setting the bits of the register in `mask`
+    pub const fn masked(name: &'static str, address: usize, mask: u32, value: u32) -> Self {
+        RegisterRule { name, address, mask, value: value & mask, write_only: 0 }
+    }
+
+    /// Value to write to the register when it currently holds `current`
+    #[inline]
+    pub fn written(&self, current: u32) -> u32 {
+        (current & !self.mask) | self.value
+    }
+
+    /// Check the value read back from the register
+    pub fn check(&self, actual: u32) -> Result<(), HarmError> {
+        let mask = self.mask & !self.write_only;
+        if actual & mask == self.value & mask {
+            Ok(())
+        } else {
+            Err(HarmError::RegisterMismatch { address: self.address, expected: self.value & mask, actual: actual & mask })
+        }
+    }
+}
+
+/// Memory protection checker of a memory, one rule register per eight sectors
+///
+/// The memory is described by its non-secure alias, and each sector gets a
+/// 4-bit security level in the rule registers starting at `rules`.
+#[derive(Clone, Copy, Debug)]
+pub struct MpcBlock {
+    /// Name of the rule registers in the reference manual, for diagnostics
+    pub name: &'static str,
+    /// Address of the first rule register
+    pub rules: usize,
+    pub base: usize,
+    pub size: usize,
+    /// Size of a sector in bytes
+    pub sector: usize,
+}
+
+impl MpcBlock {
+    /// Number of rule registers of the memory
+    #[inline]
+    pub fn rule_count(&self) -> usize {
+        (self.size / self.sector + 7) / 8
+    }
+
+    /// Rules making every sector touched by a `secure` range secure and all other sectors non-secure
+    ///
+    /// Fills `out` with `rule_count()` rules and returns them. Fails if a
+    /// `non_secure` range shares a sector with a secure one, since it would
+    /// not be reachable by the non-secure world. Ranges are given by their
+    /// non-secure alias, ranges outside of the memory are ignored.
+    pub fn rules<'r>(&self, secure: &[(usize, usize)], non_secure: &[(usize, usize)], out: &'r mut [RegisterRule]) -> Result<&'r [RegisterRule], HarmError> {
+        let sectors = self.size / self.sector;
+        let out = &mut out[.. self.rule_count()];
+
+        for (word, rule) in out.iter_mut().enumerate() {
+            let mut value = 0;
+
+            for nibble in 0 .. 8 {
+                let index = word * 8 + nibble;
+                if index >= sectors {
+                    break;
+                }
+                let sector = (self.base + index * self.sector, self.sector);
+
+                let level = if secure.iter().any(|&range| overlaps(sector, range)) {
+                    if let Some(&(base, size)) = non_secure.iter().find(|&&range| overlaps(sector, range)) {
+                        return Err(HarmError::InvalidMemoryMap { base, size, reason: "shares a sector with the secure runtime" });
+                    }
+                    SECURE_PRIV
+                } else {
+                    NS_USER
+                };
+                value |= level << (nibble * 4);
+            }
+            *rule = RegisterRule::new(self.name, self.rules + word * 4, value);
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,114 @@
+use secure_rt_core::adjustment::{encode, encode_fall_through, encode_trampoline, RelocError, RelocKind, FALL_THROUGH};
+use secure_rt_core::decoder::{decode, decode_trampoline};
+
+/// `(kind, address, instruction as read from memory, target)`, assembled by `llvm-mc -triple=thumbv8m.main`
+const VECTORS: &[(RelocKind, usize, u32, usize)] = &[
+    (RelocKind::B_T1, 0x100, 0xd02b, 0x15a),            // beq 0x15a
+    (RelocKind::B_T2, 0x102, 0xe1ad, 0x460),            // b 0x460
+    (RelocKind::B_T3, 0x104, 0x81af_f050, 0x10466),     // bne.w 0x10466
+    (RelocKind::B_T4, 0x108, 0xb9ae_f210, 0x210468),    // b.w 0x210468
+    (RelocKind::BL, 0x10c, 0xf9ad_f210, 0x21046a),      // bl 0x21046a
+    (RelocKind::B_T4, 0x110, 0xbf76_f7ff, 0x0),         // b.w 0x0
+    (RelocKind::BL, 0x114, 0xff74_f7ff, 0x0),           // bl 0x0
+    (RelocKind::CBZ, 0x118, 0xb1f3, 0x158),             // cbz r3, 0x158
+    (RelocKind::CBZ, 0x11a, 0xb9e9, 0x158),             // cbnz r1, 0x158
+    (RelocKind::ADR_T1, 0x11c, 0xa20f, 0x15c),          // adr r2, 0x15c
+    (RelocKind::ADR_W, 0x11e, 0x3542_f20f, 0x462),      // adr.w r5, 0x462
+    (RelocKind::ADR_W, 0x122, 0x1624_f2af, 0x0),        // adr.w r6, 0x0
+    (RelocKind::LDR_T1, 0x126, 0x4c0d, 0x15c),          // ldr r4, [pc, #52]
+    (RelocKind::LDR_W, 0x128, 0x9336_f8df, 0x462),      // ldr.w r9, [pc, #822]
+    (RelocKind::LDR_W, 0x12c, 0xa130_f85f, 0x0),        // ldr.w r10, [pc, #-304]
+    (RelocKind::MOVW, 0x130, 0x6079_f245, 0x5679),      // movw r0, #0x5679
+    (RelocKind::MOVT, 0x134, 0x0001_f2c2, 0x2001_0000), // movt r0, #0x2001
+    (RelocKind::BLX, 0x100, 0xe802_f001, 0x1108),       // blx 0x1108 (thumbv7a)
+    (RelocKind::BLX, 0x104, 0xef7c_f7ff, 0x0),          // blx 0x0 (thumbv7a)
+];
+
+#[test]
+fn decode_matches_assembler() {
+    for &(kind, address, code, target) in VECTORS.iter() {
+        assert_eq!(decode(kind, code, address), target, "{:?} at 0x{:x}", kind, address);
+    }
+}
+
+#[test]
+fn encode_matches_assembler() {
+    for &(kind, address, code, target) in VECTORS.iter() {
+        // move the reference somewhere else and back, registers and conditions must survive
+        let other = if kind.is_pc_relative() { (address + 0x20) & !3 } else { 0xffff_ffff };
+        let moved = encode(kind, code, address, other).unwrap();
+        assert_ne!(moved, code, "{:?} at 0x{:x}", kind, address);
+        assert_eq!(encode(kind, moved, address, target), Ok(code), "{:?} at 0x{:x}", kind, address);
+    }
+}
+
+#[test]
+fn wide_branches_are_detected() {
+    for &(kind, address, code, target) in VECTORS.iter() {
+        if let RelocKind::B_T3 | RelocKind::B_T4 | RelocKind::BL | RelocKind::BLX = kind {
+            assert_eq!(decode(RelocKind::B_W, code, address), target);
+            assert_eq!(encode(RelocKind::B_W, code, address, target), encode(kind, code, address, target));
+        }
+    }
+}
+
+#[test]
+fn narrow_encodings_leave_the_next_halfword_alone() {
+    for &(kind, address, code, target) in VECTORS.iter() {
+        if kind.size() == 2 {
+            assert_eq!(encode(kind, 0xa5a5_0000 | code, address, target), Ok(0xa5a5_0000 | code));
+        }
+    }
+}
+
+#[test]
+fn fall_through_is_a_wide_branch() {
+    assert_eq!(decode(RelocKind::B_T4, FALL_THROUGH, 0x100), 0x100);
+    assert_eq!(encode_fall_through(0x108, 0x210468), Ok(0xb9ae_f210));
+    assert_eq!(encode_fall_through(0x110, 0x0), Ok(0xbf76_f7ff));
+}
+
+#[test]
+fn trampoline_round_trip() {
+    assert_eq!(decode_trampoline(encode_trampoline(0x2001_a124)), Some(0x2001_a124));
+    assert_eq!(decode_trampoline([0xb9ae_f210, 0x2001_a125]), None);
+}
+
+#[test]
+fn encode_checks_the_reach() {
+    let base = 0x1000_0000;
+    // (kind, instruction, furthest targets backwards and forwards, alignment), the PC is `base + 4`
+    let limits = [
+        (RelocKind::B_T1, 0xd02b, -256, 254, 2),
+        (RelocKind::B_T2, 0xe1ad, -2048, 2046, 2),
+        (RelocKind::B_T3, 0x81af_f050, -(1 << 20), (1 << 20) - 2, 2),
+        (RelocKind::B_T4, 0xb9ae_f210, -(1 << 24), (1 << 24) - 2, 2),
+        (RelocKind::BL, 0xf9ad_f210, -(1 << 24), (1 << 24) - 2, 2),
+        (RelocKind::BLX, 0xe802_f001, -(1 << 24), (1 << 24) - 4, 4),
+        (RelocKind::CBZ, 0xb1f3, 0, 126, 2),
+        (RelocKind::ADR_T1, 0xa20f, 0, 1020, 4),
+        (RelocKind::LDR_T1, 0x4c0d, 0, 1020, 4),
+        (RelocKind::ADR_W, 0x3542_f20f, -4095, 4095, 1),
+        (RelocKind::LDR_W, 0x9336_f8df, -4095, 4095, 1),
+    ];
+
+    for &(kind, code, min, max, align) in limits.iter() {
+        let pc = (base + 4) as isize;
+
+        for &offset in [min, max].iter() {
+            let target = (pc + offset as isize) as usize;
+            let encoded = encode(kind, code, base, target).unwrap();
+            assert_eq!(decode(kind, encoded, base), target, "{:?} to 0x{:x}", kind, target);
+        }
+        for &offset in [min - align, max + align].iter() {
+            let target = (pc + offset as isize) as usize;
+            assert!(matches!(encode(kind, code, base, target), Err(RelocError::OutOfRange { .. })), "{:?} to 0x{:x}", kind, target);
+        }
+    }
+
+    assert!(matches!(encode(RelocKind::LDR_T1, 0x4c0d, base, base + 6), Err(RelocError::Misaligned { .. })));
+    assert!(matches!(encode(RelocKind::B_W, 0xb9ae_f210, base, base + (1 << 25)),
+                     Err(RelocError::OutOfRange { kind: RelocKind::B_T4, .. })));
+    // absolute references reach anywhere
+    assert!(encode(RelocKind::MOVT, 0x0001_f2c2, base, 0xffff_ffff).is_ok());
+}
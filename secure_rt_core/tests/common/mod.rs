@@ -0,0 +1,212 @@
+#![allow(dead_code)]
+
+use std::convert::TryInto;
+use std::fmt::Debug;
+
+use secure_rt_core::adjustment::{Branch, RelocKind};
+use secure_rt_core::{Callsite, HarmError, Metadata, Object, ObjectKind, Platform, NUM_OF_BANKS};
+
+pub const FLASH_BASE: usize = 0x20000;
+pub const MSP_NS: u32 = 0x2001_0000;
+pub const SANDBOX_SIZE: usize = 0x1000;
+pub const SANDBOX_REGIONS: [(usize, usize); 2] = [(0x2001_a000, SANDBOX_SIZE), (0x2002_f000, SANDBOX_SIZE)];
+
+/// Platform backed by host memory, target addresses are mapped onto leaked buffers
+pub struct HostPlatform {
+    regions: Vec<(usize, *mut u8, usize)>,
+    pub vtor: Option<usize>,
+    /// `(base, size)` of the sandbox bank the non-secure world may execute
+    pub executable: Option<(usize, usize)>,
+    /// `(base, size)` of the flash the non-secure world may execute the original firmware from
+    pub original: Option<(usize, usize)>,
+    /// `(base, size)` of the memory the non-secure world can reach
+    pub non_secure: Vec<(usize, usize)>,
+    /// Size of the never executable data area at the end of each bank
+    pub data_size: usize,
+}
+
+impl HostPlatform {
+    pub fn new() -> Self {
+        HostPlatform { regions: Vec::new(), vtor: None, executable: None, original: None, non_secure: Vec::new(), data_size: 0 }
+    }
+
+    /// Map `content` at target address `base`
+    pub fn map(&mut self, base: usize, content: Vec<u8>) {
+        let memory = Box::leak(content.into_boxed_slice());
+        self.regions.push((base, memory.as_mut_ptr(), memory.len()));
+    }
+
+    pub fn read(&self, address: usize, length: usize) -> Vec<u8> {
+        unsafe { self.memory(address, length).to_vec() }
+    }
+
+    pub fn read32(&self, address: usize) -> u32 {
+        let word: &[u8] = unsafe { self.memory(address, 4) };
+        u32::from_le_bytes(word.try_into().unwrap())
+    }
+
+    pub fn write32(&self, address: usize, value: u32) {
+        unsafe { self.memory(address, 4) }.copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+impl Platform for HostPlatform {
+    fn set_vtor(&mut self, address: usize) {
+        self.vtor = Some(address);
+    }
+
+    unsafe fn memory<'m>(&self, address: usize, length: usize) -> &'m mut [u8] {
+        for &(base, ptr, size) in self.regions.iter() {
+            if address >= base && address + length <= base + size {
+                return std::slice::from_raw_parts_mut(ptr.add(address - base), length);
+            }
+        }
+        panic!("unmapped memory access at 0x{:x} ({} bytes)", address, length);
+    }
+
+    fn critical_section<R, F: FnOnce() -> R>(f: F) -> R {
+        f()
+    }
+
+    fn protect_sandbox(&mut self, banks: [(usize, usize); NUM_OF_BANKS], active: Option<usize>, data_size: usize) {
+        self.executable = active.map(|i| (banks[i].0, banks[i].1 - data_size));
+        self.data_size = data_size;
+    }
+
+    fn execute_original(&mut self, firmware: (usize, usize)) {
+        self.original = Some(firmware);
+    }
+
+    fn is_secure(&self, address: usize, length: usize) -> bool {
+        !self.non_secure.iter().any(|&(base, size)| address < base + size && base < address + length)
+    }
+}
+
+/// Description of a function (or a basic block) of the test firmware
+pub struct FunctionSpec {
+    pub size: u16,
+    /// emitted as `ObjectKind::BasicBlock` instead of `ObjectKind::Function`
+    pub basic_block: bool,
+    pub isr: Option<u16>,
+    /// `(src_offset, dst_index, dst_offset, kind)` of each branch, a `src_offset` of `size` is a fall-through edge
+    pub branches: Vec<(u16, u16, u16, RelocKind)>,
+    /// offsets of the return sites
+    pub callsites: Vec<u16>,
+}
+
+/// Firmware image and metadata as produced by `harm-rw`
+pub struct Firmware {
+    pub metadata: Metadata<'static>,
+    pub flash: Vec<u8>,
+}
+
+/// Lay out a vector table followed by `functions` in the flash
+///
+/// Every byte of an object is filled with its index, except for the vector table entries.
+pub fn build_firmware(functions: &[FunctionSpec]) -> Firmware {
+    const VECTOR_TABLE_SIZE: u16 = 0x40;
+
+    let mut objects = vec![ObjectKind::VectorTable(Object {
+        reloc_items: None,
+        address: FLASH_BASE,
+        size: VECTOR_TABLE_SIZE,
+        index: 0,
+    })];
+    let mut branches = Vec::new();
+    let mut callsites = Vec::new();
+    let mut flash = vec![0u8; VECTOR_TABLE_SIZE as usize];
+    flash[0 .. 4].copy_from_slice(&MSP_NS.to_le_bytes());
+
+    for (i, func) in functions.iter().enumerate() {
+        let index = i as u16 + 1;
+        let address = FLASH_BASE + flash.len();
+        let reloc_items = if func.branches.is_empty() {
+            None
+        } else {
+            Some((branches.len() as u16, (branches.len() + func.branches.len()) as u16))
+        };
+
+        for &(src_offset, dst_index, dst_offset, kind) in func.branches.iter() {
+            branches.push(Branch { src_offset, dst_object: dst_index, dst_offset, kind });
+        }
+        for &offset in func.callsites.iter() {
+            callsites.push(Callsite { offset, caller: index });
+        }
+        if let Some(isr) = func.isr {
+            let entry = (isr as usize) << 2;
+            flash[entry .. entry + 4].copy_from_slice(&(address as u32 | 1).to_le_bytes());
+        }
+
+        flash.extend(std::iter::repeat(index as u8).take(func.size as usize));
+        while flash.len() % 4 != 0 {
+            flash.push(0);
+        }
+
+        let object = Object { reloc_items, address, size: func.size, index };
+        objects.push(if func.basic_block { ObjectKind::BasicBlock(object) } else { ObjectKind::Function(object) });
+    }
+
+    let objects: &'static [ObjectKind] = Box::leak(objects.into_boxed_slice());
+    let vectors: Vec<(&'static ObjectKind, u16)> = functions.iter().enumerate()
+        .filter_map(|(i, func)| func.isr.map(|isr| (&objects[i + 1], isr)))
+        .collect();
+
+    Firmware {
+        metadata: Metadata {
+            objects,
+            branches: Box::leak(branches.into_boxed_slice()),
+            vectors: Box::leak(vectors.into_boxed_slice()),
+            callsites: Box::leak(callsites.into_boxed_slice()),
+        },
+        flash,
+    }
+}
+
+/// Turn objects `indices` of `firmware` into `Data` objects, literal pools moved out of the code
+pub fn with_data(firmware: Firmware, indices: &[u16]) -> Firmware {
+    let objects: Vec<ObjectKind> = firmware.metadata.objects.iter().map(|object| {
+        let obj = object.get_object();
+        let copy = Object { reloc_items: obj.reloc_items, address: obj.address, size: obj.size, index: obj.index };
+        match object {
+            _ if indices.contains(&obj.index) => ObjectKind::Data(copy),
+            ObjectKind::VectorTable(_) => ObjectKind::VectorTable(copy),
+            ObjectKind::Function(_) => ObjectKind::Function(copy),
+            ObjectKind::BasicBlock(_) => ObjectKind::BasicBlock(copy),
+            ObjectKind::Data(_) => ObjectKind::Data(copy),
+        }
+    }).collect();
+
+    let objects: &'static [ObjectKind] = Box::leak(objects.into_boxed_slice());
+    let vectors: Vec<(&'static ObjectKind, u16)> = firmware.metadata.vectors.iter()
+        .map(|&(object, isr)| (&objects[object.get_object().index as usize], isr))
+        .collect();
+
+    Firmware {
+        metadata: Metadata { objects, vectors: Box::leak(vectors.into_boxed_slice()), ..firmware.metadata },
+        flash: firmware.flash,
+    }
+}
+
+/// Map the flash and the sandbox of `firmware` into a new host platform
+pub fn host_platform(firmware: &Firmware) -> HostPlatform {
+    let mut platform = HostPlatform::new();
+    platform.map(FLASH_BASE, firmware.flash.clone());
+    for &(base, size) in SANDBOX_REGIONS.iter() {
+        platform.map(base, vec![0u8; size]);
+    }
+    platform
+}
+
+pub fn leak_dispatch_tbl(firmware: &Firmware) -> &'static mut [u32] {
+    Box::leak(vec![0u32; firmware.metadata.objects.len()].into_boxed_slice())
+}
+
+/// Reason of the rejected sandbox bank, memory map or stack window `result` holds
+pub fn reason<T: Debug>(result: Result<T, HarmError>) -> &'static str {
+    match result {
+        Err(HarmError::InvalidSandbox { reason, .. })
+        | Err(HarmError::InvalidMemoryMap { reason, .. })
+        | Err(HarmError::InvalidStackWindow { reason, .. }) => reason,
+        other => panic!("expected an invalid sandbox, memory map or stack window, got {:?}", other),
+    }
+}
@@ -0,0 +1,40 @@
+use secure_rt_core::entropy::{log2_q8, SEED_ENTROPY};
+use secure_rt_core::Entropy;
+
+#[test]
+fn log2_of_powers_of_two_is_exact() {
+    for bits in 0 .. 32 {
+        assert_eq!(log2_q8(1 << bits), bits << 8);
+    }
+    assert_eq!(log2_q8(0), 0);
+}
+
+#[test]
+fn log2_is_rounded_down() {
+    for &x in [3u32, 5, 6, 7, 33, 1000, 0xffff_ffff].iter() {
+        let exact = (x as f64).log2() * 256.0;
+        let q8 = log2_q8(x) as f64;
+        assert!(q8 <= exact && exact < q8 + 1.0, "log2_q8({}) = {}, expected {}", x, q8, exact);
+    }
+    assert_eq!(log2_q8(3), 405);
+}
+
+#[test]
+fn entropy_is_displayed_in_bits() {
+    let entropy = Entropy { order: log2_q8(120), padding: 5 << 8 };
+    assert_eq!(entropy.bits(), 11);
+    assert_eq!(format!("{}", entropy), "11.90 bits (order 6.90, padding 5.00)");
+}
+
+#[test]
+fn entropy_is_capped_by_the_seed() {
+    let entropy = Entropy { order: (301 << 8) | 205, padding: (110 << 8) | 187 };
+    assert!(entropy.is_capped());
+    assert_eq!(entropy.total(), SEED_ENTROPY);
+    assert_eq!(entropy.bits(), 256);
+    assert_eq!(format!("{}", entropy), "256.00 bits, capped by the seed (order 301.80, padding 110.73)");
+
+    let entropy = Entropy { order: SEED_ENTROPY, padding: 0 };
+    assert!(!entropy.is_capped());
+    assert_eq!(format!("{}", entropy), "256.00 bits (order 256.00, padding 0.00)");
+}
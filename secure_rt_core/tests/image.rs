@@ -0,0 +1,211 @@
+mod common;
+
+use common::*;
+use secure_rt_core::adjustment::RelocKind;
+use secure_rt_core::image::{self, SIGNATURE_SIZE};
+use secure_rt_core::{HarmError, Metadata, ObjectKind, Platform};
+
+const IMAGE_BASE: usize = 0x7_0000;
+const KEY_SEED: [u8; 32] = [7; 32];
+
+fn sample_firmware() -> Firmware {
+    build_firmware(&[
+        FunctionSpec { basic_block: false, size: 12, isr: Some(1), branches: vec![(2, 2, 0, RelocKind::BL), (6, 2, 1, RelocKind::MOVW)], callsites: vec![6] },
+        FunctionSpec { basic_block: true, size: 8, isr: None, branches: vec![(8, 1, 4, RelocKind::B_T4)], callsites: vec![2, 4] },
+    ])
+}
+
+/// `(kind, reloc_items, address, size, index)` of an object
+type ObjectRow = (u8, Option<(u16, u16)>, usize, u16, u16);
+
+/// `(src_offset, dst_object, dst_offset, kind)` of a branch
+type BranchRow = (u16, u16, u16, RelocKind);
+
+/// Objects, branches, vectors and callsites as plain tuples
+type Tables = (Vec<ObjectRow>, Vec<BranchRow>, Vec<(u16, u16)>, Vec<(u16, u16)>);
+
+/// Tables of `metadata`, to compare metadata read back from an image
+fn tables(metadata: &Metadata) -> Tables {
+    let objects = metadata.objects.iter().map(|object| {
+        let obj = object.get_object();
+        let kind = match object {
+            ObjectKind::VectorTable(_) => 0,
+            ObjectKind::Function(_) => 1,
+            ObjectKind::BasicBlock(_) => 2,
+            ObjectKind::Data(_) => 3,
+        };
+        (kind, obj.reloc_items, obj.address, obj.size, obj.index)
+    }).collect();
+    let branches = metadata.branches.iter().map(|b| (b.src_offset, b.dst_object, b.dst_offset, b.kind)).collect();
+    let vectors = metadata.vectors.iter().map(|&(isr, vector)| (isr.get_object().index, vector)).collect();
+    let callsites = metadata.callsites.iter().map(|cs| (cs.offset, cs.caller)).collect();
+    (objects, branches, vectors, callsites)
+}
+
+/// Map the signed image of `metadata` in the flash of a new host platform
+fn flash_signed(metadata: &Metadata, key_seed: &[u8; 32]) -> (HostPlatform, usize) {
+    let mut signed = image::serialize(metadata);
+    let size = signed.len();
+    signed.extend_from_slice(&image::sign(&signed, key_seed));
+
+    let mut platform = HostPlatform::new();
+    platform.map(IMAGE_BASE, signed);
+    (platform, size)
+}
+
+#[test]
+fn image_round_trip() {
+    let firmware = sample_firmware();
+    let bytes = image::serialize(&firmware.metadata);
+    let parsed = image::parse(&bytes).unwrap();
+
+    assert_eq!(tables(&parsed), tables(&firmware.metadata));
+}
+
+#[test]
+fn data_objects_round_trip() {
+    let firmware = with_data(build_firmware(&[
+        FunctionSpec { basic_block: false, size: 12, isr: Some(1), branches: vec![(0, 2, 4, RelocKind::LDR_W)], callsites: vec![] },
+        FunctionSpec { basic_block: false, size: 8, isr: None, branches: vec![], callsites: vec![] },
+    ]), &[2]);
+    let bytes = image::serialize(&firmware.metadata);
+    let parsed = image::parse(&bytes).unwrap();
+
+    assert!(parsed.objects[2].is_data());
+    assert_eq!(tables(&parsed), tables(&firmware.metadata));
+}
+
+#[test]
+fn signed_image_is_loaded() {
+    let firmware = sample_firmware();
+    let (platform, _) = flash_signed(&firmware.metadata, &KEY_SEED);
+
+    let loaded = image::load_signed(&platform, IMAGE_BASE, &image::public_key(&KEY_SEED)).unwrap();
+    assert_eq!(tables(&loaded), tables(&firmware.metadata));
+}
+
+#[test]
+fn tampered_image_is_rejected() {
+    let firmware = sample_firmware();
+    let public_key = image::public_key(&KEY_SEED);
+
+    // any flipped bit, in the tables or in the signature
+    let (platform, size) = flash_signed(&firmware.metadata, &KEY_SEED);
+    for &offset in [20, size - 1, size, size + SIGNATURE_SIZE - 1].iter() {
+        let word = platform.read32(IMAGE_BASE + (offset & !3));
+        platform.write32(IMAGE_BASE + (offset & !3), word ^ (1 << ((offset & 3) * 8)));
+        assert_eq!(image::load_signed(&platform, IMAGE_BASE, &public_key).err(), Some(HarmError::BadSignature), "byte {}", offset);
+        platform.write32(IMAGE_BASE + (offset & !3), word);
+    }
+
+    // signed by another key
+    let (platform, _) = flash_signed(&firmware.metadata, &[8; 32]);
+    assert_eq!(image::load_signed(&platform, IMAGE_BASE, &public_key).err(), Some(HarmError::BadSignature));
+}
+
+#[test]
+fn malformed_image_is_rejected() {
+    let firmware = sample_firmware();
+    let image = image::serialize(&firmware.metadata);
+
+    let mut bad_magic = image.clone();
+    bad_magic[0] = b'X';
+    assert!(matches!(image::parse(&bad_magic), Err(HarmError::MalformedImage { offset: 0, .. })));
+
+    let mut bad_version = image.clone();
+    bad_version[4] = 0xff;
+    assert!(matches!(image::parse(&bad_version), Err(HarmError::MalformedImage { offset: 4, .. })));
+
+    assert!(matches!(image::parse(&image[.. image.len() - 1]), Err(HarmError::MalformedImage { .. })));
+    assert!(matches!(image::parse(&image[.. 8]), Err(HarmError::MalformedImage { .. })));
+
+    // kind of the first object
+    let mut bad_kind = image;
+    bad_kind[image::HEADER_SIZE + 12] = 9;
+    assert!(matches!(image::parse(&bad_kind), Err(HarmError::MalformedImage { .. })));
+}
+
+#[test]
+fn branches_and_callsites_are_used_in_place() {
+    let firmware = sample_firmware();
+    let bytes = image::serialize(&firmware.metadata);
+    let parsed = image::parse(&bytes).unwrap();
+
+    let range = bytes.as_ptr_range();
+    assert!(range.contains(&(parsed.branches.as_ptr() as *const u8)));
+    assert!(range.contains(&(parsed.callsites.as_ptr() as *const u8)));
+    assert_eq!(parsed.branches.len(), firmware.metadata.branches.len());
+    assert_eq!(parsed.callsites.len(), firmware.metadata.callsites.len());
+}
+
+#[test]
+fn reachable_image_is_copied() {
+    let firmware = sample_firmware();
+    let public_key = image::public_key(&KEY_SEED);
+    let (mut platform, size) = flash_signed(&firmware.metadata, &KEY_SEED);
+    let flash = unsafe { platform.memory(IMAGE_BASE, size) }.as_ptr_range();
+
+    // out of reach of the non-secure world, used in place
+    let loaded = image::load_signed(&platform, IMAGE_BASE, &public_key).unwrap();
+    assert!(flash.contains(&(loaded.branches.as_ptr() as *const u8)));
+
+    // reachable, changing the flash after the verification changes nothing
+    platform.non_secure.push((IMAGE_BASE, size + SIGNATURE_SIZE));
+    let loaded = image::load_signed(&platform, IMAGE_BASE, &public_key).unwrap();
+    assert!(!flash.contains(&(loaded.branches.as_ptr() as *const u8)));
+    assert!(!flash.contains(&(loaded.callsites.as_ptr() as *const u8)));
+    for offset in (0 .. size).step_by(4) {
+        platform.write32(IMAGE_BASE + offset, 0xffff_ffff);
+    }
+    assert_eq!(tables(&loaded), tables(&firmware.metadata));
+}
+
+/// A platform knowing nothing of the security of its memory
+struct UnknownPlatform(HostPlatform);
+
+impl Platform for UnknownPlatform {
+    fn set_vtor(&mut self, address: usize) {
+        self.0.set_vtor(address)
+    }
+
+    unsafe fn memory<'m>(&self, address: usize, length: usize) -> &'m mut [u8] {
+        self.0.memory(address, length)
+    }
+
+    fn critical_section<R, F: FnOnce() -> R>(f: F) -> R {
+        HostPlatform::critical_section(f)
+    }
+}
+
+#[test]
+fn image_is_copied_unless_proven_secure() {
+    let firmware = sample_firmware();
+    let public_key = image::public_key(&KEY_SEED);
+    let (platform, size) = flash_signed(&firmware.metadata, &KEY_SEED);
+    let flash = unsafe { platform.memory(IMAGE_BASE, size) }.as_ptr_range();
+
+    let loaded = image::load_signed(&UnknownPlatform(platform), IMAGE_BASE, &public_key).unwrap();
+    assert!(!flash.contains(&(loaded.branches.as_ptr() as *const u8)));
+    assert!(!flash.contains(&(loaded.callsites.as_ptr() as *const u8)));
+}
+
+#[test]
+fn misaligned_image_is_rejected() {
+    let firmware = sample_firmware();
+    let bytes = image::serialize(&firmware.metadata);
+
+    // the same image one byte off its (aligned) allocation
+    let mut shifted = vec![0u8; bytes.len() + 1];
+    shifted[1 ..].copy_from_slice(&bytes);
+    assert!(matches!(image::Image::new(&shifted[1 ..]), Err(HarmError::MalformedImage { reason: "misaligned image", .. })));
+}
+
+#[test]
+fn unknown_reloc_kind_is_rejected() {
+    let firmware = sample_firmware();
+    let mut bytes = image::serialize(&firmware.metadata);
+    let first_branch = image::HEADER_SIZE + firmware.metadata.objects.len() * image::OBJECT_SIZE;
+
+    bytes[first_branch + 6] = 14;
+    assert!(matches!(image::parse(&bytes), Err(HarmError::MalformedImage { reason: "unknown relocation kind", .. })));
+}
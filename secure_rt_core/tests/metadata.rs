@@ -0,0 +1,116 @@
+mod common;
+
+use common::*;
+use secure_rt_core::adjustment::RelocKind;
+use secure_rt_core::{validate_metadata, HarmError, Metadata, Object, ObjectKind};
+
+#[test]
+fn valid_metadata_passes() {
+    let firmware = build_firmware(&[
+        FunctionSpec { basic_block: false, size: 12, isr: Some(1), branches: vec![(2, 2, 0, RelocKind::BL)], callsites: vec![6] },
+        FunctionSpec { basic_block: true, size: 8, isr: None, branches: vec![(8, 1, 4, RelocKind::B_T4)], callsites: vec![] },
+    ]);
+
+    assert_eq!(validate_metadata(&firmware.metadata, &SANDBOX_REGIONS), Ok(()));
+}
+
+#[test]
+fn all_violations_are_reported() {
+    let firmware = build_firmware(&[
+        FunctionSpec {
+            basic_block: false,
+            size: 8,
+            isr: None,
+            branches: vec![(6, 2, 0, RelocKind::BL), (0, 9, 0, RelocKind::BL), (2, 0, 0, RelocKind::B_T2), (4, 2, 8, RelocKind::B_T2)],
+            callsites: vec![10],
+        },
+        FunctionSpec { basic_block: false, size: 8, isr: None, branches: vec![], callsites: vec![] },
+    ]);
+    let objects = firmware.metadata.objects;
+    let metadata = Metadata { vectors: Box::leak(Box::new([(&objects[1], 20), (&objects[0], 2)])), ..firmware.metadata };
+
+    let inconsistent = |object, reason| HarmError::MetadataInconsistency { object, reason };
+    assert_eq!(validate_metadata(&metadata, &SANDBOX_REGIONS), Err(vec![
+        inconsistent(1, "reloc item past the end of its object"),
+        inconsistent(1, "reloc item targets no object"),
+        HarmError::InvalidRelocation { object: 1, offset: 2 },
+        inconsistent(1, "reloc item targets past the end of its object"),
+        inconsistent(1, "vector number out of the vector table"),
+        inconsistent(0, "interrupt handler is not a function"),
+        inconsistent(1, "callsite past the end of its caller"),
+    ]));
+}
+
+#[test]
+fn broken_object_table_is_reported() {
+    let object = |index, address, size| Object { reloc_items: None, address, size, index };
+    let objects: &'static [ObjectKind] = Box::leak(Box::new([
+        ObjectKind::Function(object(0, FLASH_BASE, 0x40)),
+        ObjectKind::Function(object(1, FLASH_BASE + 0x40, 0x10)),
+        ObjectKind::Function(object(3, FLASH_BASE + 0x48, 0x10)),
+        ObjectKind::VectorTable(object(3, FLASH_BASE + 0x60, 0x40)),
+    ]));
+    let metadata = Metadata { objects, branches: &[], vectors: &[], callsites: &[] };
+
+    let violations = validate_metadata(&metadata, &SANDBOX_REGIONS).unwrap_err();
+    assert!(violations.contains(&HarmError::MetadataInconsistency { object: 2, reason: "index does not match its position in the table" }));
+    assert!(violations.contains(&HarmError::MetadataInconsistency { object: 3, reason: "second vector table" }));
+    assert!(violations.contains(&HarmError::MetadataInconsistency { object: 2, reason: "overlaps another object in the flash" }));
+    assert!(violations.contains(&HarmError::MissingVectorTable));
+}
+
+#[test]
+fn oversized_firmware_is_reported() {
+    let firmware = build_firmware(&[
+        FunctionSpec { basic_block: false, size: 0x600, isr: None, branches: vec![], callsites: vec![] },
+        FunctionSpec { basic_block: false, size: 0x600, isr: None, branches: vec![], callsites: vec![] },
+    ]);
+
+    // the smallest bank decides
+    let banks = [(0x3000_0000, 0x1000), (0x3001_0000, 0xa00)];
+    match validate_metadata(&firmware.metadata, &banks) {
+        Err(violations) => match violations[..] {
+            [HarmError::SandboxExhausted { object: 2, needed, left }] => assert!(needed > left),
+            _ => panic!("unexpected {:?}", violations),
+        },
+        result => panic!("unexpected {:?}", result),
+    }
+}
+
+#[test]
+fn data_filling_a_bank_is_reported() {
+    let firmware = with_data(build_firmware(&[
+        FunctionSpec { basic_block: false, size: 0x9f0, isr: None, branches: vec![], callsites: vec![] },
+    ]), &[1]);
+
+    // the data area takes the whole smaller bank, no room is left for the code, not even the vector table
+    let banks = [(0x3000_0000, 0x1000), (0x3001_0000, 0xa00)];
+    match validate_metadata(&firmware.metadata, &banks) {
+        Err(violations) => match violations[..] {
+            [HarmError::SandboxExhausted { object: 0, needed, left: 0 }] => assert!(needed > 0),
+            _ => panic!("unexpected {:?}", violations),
+        },
+        result => panic!("unexpected {:?}", result),
+    }
+}
+
+#[test]
+fn data_is_never_branched_to() {
+    let firmware = with_data(build_firmware(&[
+        FunctionSpec {
+            basic_block: false,
+            size: 12,
+            isr: Some(1),
+            branches: vec![(0, 2, 0, RelocKind::MOVW), (4, 2, 0, RelocKind::BL), (8, 2, 0, RelocKind::LDR_W), (12, 2, 0, RelocKind::B_T4)],
+            callsites: vec![8],
+        },
+        FunctionSpec { basic_block: false, size: 8, isr: None, branches: vec![], callsites: vec![] },
+        FunctionSpec { basic_block: false, size: 8, isr: None, branches: vec![(0, 1, 0, RelocKind::B_T2)], callsites: vec![] },
+    ]), &[2, 3]);
+
+    assert_eq!(validate_metadata(&firmware.metadata, &SANDBOX_REGIONS), Err(vec![
+        HarmError::InvalidRelocation { object: 1, offset: 4 },
+        HarmError::InvalidRelocation { object: 1, offset: 12 },
+        HarmError::MetadataInconsistency { object: 3, reason: "data object with reloc items" },
+    ]));
+}
@@ -0,0 +1,780 @@
+mod common;
+
+use common::*;
+use secure_rt_core::adjustment::{self, RelocKind};
+use secure_rt_core::decoder;
+use secure_rt_core::entropy::log2_q8;
+use secure_rt_core::error::TRAMPOLINE_OBJECT;
+use secure_rt_core::{validate_metadata, ChaChaDrbg, CodeProtection, HarmError, Metadata, ObjectKind, PaddingPolicy, Randomizer, SeededRandom, Trap};
+
+fn sample_functions() -> Vec<FunctionSpec> {
+    vec![
+        // 1 - reset handler
+        FunctionSpec { basic_block: false, size: 24, isr: Some(1), branches: vec![(4, 2, 0, RelocKind::BL), (12, 3, 0, RelocKind::B_T4)], callsites: vec![8, 16] },
+        // 2
+        FunctionSpec { basic_block: false, size: 10, isr: None, branches: vec![], callsites: vec![] },
+        // 3 - interrupt handler
+        FunctionSpec { basic_block: false, size: 6, isr: Some(15), branches: vec![], callsites: vec![] },
+        // 4
+        FunctionSpec { basic_block: false, size: 32, isr: None, branches: vec![(0, 2, 0, RelocKind::B_T3)], callsites: vec![4] },
+    ]
+}
+
+fn relocated_words(firmware: &Firmware, index: usize) -> Vec<(usize, usize)> {
+    match &firmware.metadata.objects[index] {
+        ObjectKind::Function(obj) | ObjectKind::BasicBlock(obj) => firmware.metadata.get_reloc_items(obj)
+            .map_or(Vec::new(), |items| items.iter().map(|item| (item.src_offset as usize, item.kind.size())).collect()),
+        _ => Vec::new(),
+    }
+}
+
+fn check_layout(randomizer: &mut Randomizer<HostPlatform, SeededRandom>, firmware: &Firmware, bank: (usize, usize)) {
+    let objects = firmware.metadata.objects;
+    let data_area = bank.0 + bank.1 - firmware.metadata.get_data_size();
+    let mut ranges = Vec::new();
+
+    for (i, object) in objects.iter().enumerate() {
+        let obj = object.get_object();
+        let address = randomizer.get_instance_address(i);
+        let size = firmware.metadata.get_instance_size(obj);
+
+        // inside the active bank and aligned like the original
+        assert!(address >= bank.0 && address + size <= bank.0 + bank.1, "object {} is outside of the bank", i);
+        match object {
+            ObjectKind::VectorTable(_) => assert_eq!(address % 128, 0),
+            ObjectKind::Function(_) | ObjectKind::BasicBlock(_) => assert_eq!(address % 4, obj.get_address() % 4),
+            ObjectKind::Data(_) => assert_eq!(address % 8, 0),
+        }
+        // code and data never share the end of the bank
+        assert_eq!(address >= data_area, object.is_data(), "object {} is on the wrong side of the data area", i);
+        ranges.push((address, address + size));
+
+        // the code is copied, except for the words being relocated
+        let code = randomizer.platform().read(address, obj.get_size());
+        let origin = randomizer.platform().read(obj.get_address(), obj.get_size());
+        let relocated = relocated_words(firmware, i);
+        for offset in 0 .. obj.get_size() {
+            if let ObjectKind::VectorTable(_) = object {
+                if offset >= 4 {
+                    continue;
+                }
+            }
+            if relocated.iter().any(|&(r, width)| offset >= r && offset < r + width) {
+                continue;
+            }
+            assert_eq!(code[offset], origin[offset], "object {} differs at offset {}", i, offset);
+        }
+
+        // every relocated instruction refers to its target in the same layout
+        for item in firmware.metadata.get_reloc_items(obj).unwrap_or(&[]).iter() {
+            let src_addr = address + item.src_offset as usize;
+            let dst_addr = randomizer.get_instance_address(item.dst_object as usize) + item.dst_offset as usize;
+            let kind = if item.src_offset as usize == obj.get_size() { RelocKind::B_T4 } else { item.kind };
+            let code = randomizer.platform().read32(src_addr);
+            let target = decoder::decode(kind, code, src_addr);
+            match kind {
+                RelocKind::MOVW => assert_eq!(target, dst_addr & 0xffff, "object {} at offset {}", i, item.src_offset),
+                RelocKind::MOVT => assert_eq!(target, dst_addr & 0xffff_0000, "object {} at offset {}", i, item.src_offset),
+                _ if target != dst_addr => {
+                    // out of reach, through a trampoline
+                    assert_eq!(randomizer.platform().read32(target), 0xf000_f8df, "object {} at offset {}", i, item.src_offset);
+                    assert_eq!(randomizer.platform().read32(target + 4), dst_addr as u32 | 1, "object {} at offset {}", i, item.src_offset);
+                },
+                _ => (),
+            }
+        }
+
+        // the object can be found by address
+        let (found, offset) = randomizer.lookup(address + size - 1).unwrap();
+        assert_eq!(found.get_object().index as usize, i);
+        assert_eq!(offset, size - 1);
+    }
+
+    ranges.sort();
+    for pair in ranges.windows(2) {
+        assert!(pair[0].1 <= pair[1].0, "objects overlap");
+    }
+}
+
+#[test]
+fn randomize_places_all_objects() {
+    let firmware = build_firmware(&sample_functions());
+    let platform = host_platform(&firmware);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe { Randomizer::new(platform, SeededRandom::new(0x1234_5678), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl) };
+
+    randomizer.randomize().unwrap();
+    check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[0]);
+
+    // the vector table is installed and refers to the new interrupt handlers
+    let vtor = randomizer.platform().vtor.unwrap();
+    assert_eq!(vtor, randomizer.get_instance_address(0));
+    assert_eq!(randomizer.platform().read32(vtor), MSP_NS);
+    for &(isr, vector) in firmware.metadata.vectors.iter() {
+        let address = randomizer.get_instance_address(isr.get_object().index as usize);
+        assert_eq!(randomizer.platform().read32(vtor + ((vector as usize) << 2)), address as u32 | 1);
+    }
+}
+
+#[test]
+fn randomize_alternates_banks() {
+    let firmware = build_firmware(&sample_functions());
+    let platform = host_platform(&firmware);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe { Randomizer::new(platform, SeededRandom::new(42), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl) };
+
+    for epoch in 0 .. 6 {
+        randomizer.randomize().unwrap();
+        check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[epoch % 2]);
+    }
+}
+
+#[test]
+fn staged_layout_leaves_running_layout_alone() {
+    let firmware = build_firmware(&sample_functions());
+    let platform = host_platform(&firmware);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe { Randomizer::new(platform, SeededRandom::new(7), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl) };
+
+    randomizer.randomize().unwrap();
+    let (base, size) = SANDBOX_REGIONS[0];
+    let running = randomizer.platform().read(base, size);
+    let vtor = randomizer.platform().vtor;
+
+    randomizer.shuffle().unwrap();
+    randomizer.ref_adjust().unwrap();
+    assert_eq!(randomizer.platform().read(base, size), running);
+    assert_eq!(randomizer.platform().vtor, vtor);
+
+    randomizer.commit();
+    check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[1]);
+}
+
+#[test]
+fn shuffle_without_objects() {
+    let firmware = build_firmware(&[]);
+    let metadata = Metadata { objects: &[], vectors: &[], ..firmware.metadata };
+    let platform = host_platform(&firmware);
+    let mut randomizer = unsafe { Randomizer::new(platform, SeededRandom::new(5), metadata, SANDBOX_REGIONS, &mut []) };
+
+    randomizer.shuffle().unwrap();
+    assert_eq!(randomizer.entropy().order, 0);
+}
+
+#[test]
+fn fixup_stack_moves_return_addresses_and_frames() {
+    const STACK_BASE: usize = 0x2000_f000;
+    const STACK_SIZE: usize = 0x40;
+
+    let firmware = build_firmware(&sample_functions());
+    let mut platform = host_platform(&firmware);
+    platform.map(STACK_BASE, vec![0u8; STACK_SIZE]);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe { Randomizer::new(platform, SeededRandom::new(99), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl) };
+
+    randomizer.randomize().unwrap();
+
+    let reset = randomizer.get_instance_address(1);
+    let func4 = randomizer.get_instance_address(4);
+    let stack = [
+        // exception frame: r0 - r3, r12, lr, pc, xpsr
+        0x1234_5678,
+        // an address into object 4 followed by a word with the Thumb bit, but not at the PC of the frame
+        (func4 + 2) as u32,
+        1 << 24,
+        0,
+        0,
+        (reset + 8) as u32 | 1,
+        (func4 + 6) as u32,
+        1 << 24,
+        // return address at a callsite of object 1
+        (reset + 8) as u32 | 1,
+        // return address into object 1 that is not a callsite
+        (reset + 10) as u32 | 1,
+    ];
+    for (i, word) in stack.iter().enumerate() {
+        randomizer.platform().write32(STACK_BASE + i * 4, *word);
+    }
+
+    randomizer.shuffle().unwrap();
+    randomizer.ref_adjust().unwrap();
+    unsafe { randomizer.fixup_stack(STACK_BASE, STACK_BASE + stack.len() * 4) };
+    randomizer.commit();
+
+    let reset = randomizer.get_instance_address(1);
+    let func4 = randomizer.get_instance_address(4);
+    let platform = randomizer.platform();
+    let expected = [
+        0x1234_5678,
+        stack[1],
+        1 << 24,
+        0,
+        0,
+        (reset + 8) as u32 | 1,
+        (func4 + 6) as u32,
+        1 << 24,
+        (reset + 8) as u32 | 1,
+        stack[9],
+    ];
+    for (i, word) in expected.iter().enumerate() {
+        assert_eq!(platform.read32(STACK_BASE + i * 4), *word, "word {} of the stack", i);
+    }
+}
+
+#[test]
+fn seed_reproduces_layout() {
+    let firmware = build_firmware(&sample_functions());
+    let seeds = [[1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]];
+    let padding = PaddingPolicy { max_gap: 32, trap: Trap::Udf };
+
+    // the runtime: one randomizer, a fresh seed for every epoch
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut runtime = unsafe {
+        Randomizer::new(host_platform(&firmware), ChaChaDrbg::from_seed(seeds[0]), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    runtime.set_padding(padding);
+
+    for (epoch, &seed) in seeds.iter().enumerate() {
+        *runtime.rng() = ChaChaDrbg::from_seed(seed);
+        let bank = runtime.next_bank();
+        runtime.shuffle().unwrap();
+        runtime.ref_adjust().unwrap();
+        // as if the layout of epoch 1 had failed, the next one is built in the same bank
+        if epoch == 1 {
+            assert_eq!(runtime.next_bank(), bank);
+            continue;
+        }
+        runtime.commit();
+
+        // the triage tool: a fresh randomizer starting in the recorded bank
+        let mut banks = SANDBOX_REGIONS;
+        banks.rotate_left(bank);
+        let dispatch_tbl = leak_dispatch_tbl(&firmware);
+        let mut replay = unsafe {
+            Randomizer::new(host_platform(&firmware), ChaChaDrbg::from_seed(seed), firmware.metadata, banks, dispatch_tbl)
+        };
+        replay.set_padding(runtime.padding());
+        replay.randomize().unwrap();
+
+        for i in 0 .. firmware.metadata.objects.len() {
+            assert_eq!(replay.get_instance_address(i), runtime.get_instance_address(i));
+        }
+    }
+}
+
+#[test]
+fn padding_fills_gaps_with_traps() {
+    let firmware = build_firmware(&sample_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(7), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    randomizer.set_padding(PaddingPolicy { max_gap: 64, trap: Trap::Bkpt });
+
+    for epoch in 0 .. 4 {
+        let bank = SANDBOX_REGIONS[epoch % 2];
+        randomizer.randomize().unwrap();
+        check_layout(&mut randomizer, &firmware, bank);
+
+        // 5 objects in any order, and a choice among 17 word-aligned gaps before each function
+        let entropy = randomizer.entropy();
+        assert_eq!(entropy.order >> 8, 6);
+        assert_eq!(entropy.padding, 4 * log2_q8(17));
+
+        // everything outside of the objects is a trap
+        let ranges: Vec<(usize, usize)> = firmware.metadata.objects.iter().enumerate()
+            .map(|(i, object)| {
+                let address = randomizer.get_instance_address(i);
+                (address, address + firmware.metadata.get_instance_size(object.get_object()))
+            })
+            .collect();
+        let memory = randomizer.platform().read(bank.0, bank.1);
+        for offset in (0 .. bank.1).step_by(2) {
+            let address = bank.0 + offset;
+            if ranges.iter().all(|&(start, end)| address < start || address >= end) {
+                assert_eq!(&memory[offset .. offset + 2], &Trap::Bkpt.encoding().to_le_bytes(), "no trap at 0x{:x}", address);
+            }
+        }
+    }
+}
+
+#[test]
+fn padding_entropy_counts_distinct_addresses() {
+    // a vector table, aligned to 128 bytes, and a word-aligned function
+    let firmware = build_firmware(&[FunctionSpec { basic_block: false, size: 12, isr: Some(1), branches: vec![], callsites: vec![] }]);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(13), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    randomizer.set_padding(PaddingPolicy { max_gap: 256, trap: Trap::Udf });
+
+    let mut addresses = [Vec::new(), Vec::new()];
+    for epoch in 0 .. 256 {
+        randomizer.randomize().unwrap();
+        let bank = SANDBOX_REGIONS[epoch % 2].0;
+
+        // 3 places for the vector table and 65 for the function, after whatever comes first
+        let entropy = randomizer.entropy();
+        assert_eq!(entropy.padding, log2_q8(3) + log2_q8(65));
+        for (i, seen) in addresses.iter_mut().enumerate() {
+            let offset = randomizer.get_instance_address(i) - bank;
+            if !seen.contains(&offset) {
+                seen.push(offset);
+            }
+        }
+    }
+
+    // 3 gaps before or after the 65 places of the function, each gap a different address
+    addresses[0].sort_unstable();
+    assert_eq!(addresses[0], vec![0, 0x80, 0x100, 0x180, 0x200, 0x280]);
+    assert!(addresses[1].len() > 65);
+    assert!(addresses[1].iter().all(|offset| offset % 4 == 0));
+}
+
+#[test]
+fn padding_is_bounded_by_the_bank() {
+    let firmware = build_firmware(&sample_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(11), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    randomizer.set_padding(PaddingPolicy { max_gap: SANDBOX_SIZE * 4, trap: Trap::Udf });
+
+    for epoch in 0 .. 16 {
+        randomizer.randomize().unwrap();
+        check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[epoch % 2]);
+    }
+}
+
+#[test]
+fn no_padding_packs_objects() {
+    let firmware = build_firmware(&sample_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(3), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+
+    randomizer.randomize().unwrap();
+    let entropy = randomizer.entropy();
+    assert_eq!(entropy.padding, 0);
+    assert_eq!(entropy.bits(), 6);
+
+    // the objects only leave room for their alignment
+    let end = (0 .. firmware.metadata.objects.len())
+        .map(|i| randomizer.get_instance_address(i) + firmware.metadata.get_instance_size(firmware.metadata.objects[i].get_object()))
+        .max()
+        .unwrap();
+    let total: usize = firmware.metadata.objects.iter().map(|object| object.get_object().get_size()).sum();
+    assert!(end - SANDBOX_REGIONS[0].0 < total + 128 + 4 * 4);
+}
+
+/// Two large functions branching to each other with narrow branches, which cannot reach in every order
+fn distant_functions() -> Vec<FunctionSpec> {
+    vec![
+        // 1 - reset handler, branches to 2 at its end
+        FunctionSpec { basic_block: false, size: 0x900, isr: Some(1), branches: vec![(0x8fc, 2, 0, RelocKind::B_T2)], callsites: vec![] },
+        // 2 - branches back to 1 at its start
+        FunctionSpec { basic_block: false, size: 0x600, isr: None, branches: vec![(0, 1, 0, RelocKind::B_T2)], callsites: vec![] },
+    ]
+}
+
+/// Address of the trampoline the branch at `offset` of object `index` goes through, if any
+fn trampoline_of(randomizer: &mut Randomizer<HostPlatform, SeededRandom>, index: usize, offset: usize, target: usize) -> Option<usize> {
+    let src_addr = randomizer.get_instance_address(index) + offset;
+    let code = randomizer.platform().read32(src_addr);
+    let dst_addr = decoder::decode(RelocKind::B_T2, code, src_addr);
+    if dst_addr == randomizer.get_instance_address(target) { None } else { Some(dst_addr) }
+}
+
+#[test]
+fn out_of_range_branches_use_trampolines() {
+    const STACK_BASE: usize = 0x2000_f000;
+
+    let firmware = build_firmware(&distant_functions());
+    let mut platform = host_platform(&firmware);
+    platform.map(STACK_BASE, vec![0u8; 32]);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(platform, SeededRandom::new(1), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    let mut trampolines = 0;
+    let mut epoch = 0;
+
+    for _ in 0 .. 8 {
+        randomizer.randomize().unwrap();
+        check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[epoch % 2]);
+        epoch += 1;
+
+        // a PC about to run a trampoline moves to the new instance of its target
+        let trampoline = trampoline_of(&mut randomizer, 1, 0x8fc, 2).or(trampoline_of(&mut randomizer, 2, 0, 1));
+        if let Some(trampoline) = trampoline {
+            trampolines += 1;
+            // stacked PC and xPSR of an exception frame
+            randomizer.platform().write32(STACK_BASE + 24, trampoline as u32);
+            randomizer.platform().write32(STACK_BASE + 28, 1 << 24);
+            let target = randomizer.platform().read32(trampoline + 4) as usize & !1;
+            let index = randomizer.lookup(target).unwrap().0.get_object().index as usize;
+
+            randomizer.shuffle().unwrap();
+            randomizer.ref_adjust().unwrap();
+            unsafe { randomizer.fixup_stack(STACK_BASE, STACK_BASE + 32) };
+            randomizer.commit();
+            epoch += 1;
+
+            assert_eq!(randomizer.platform().read32(STACK_BASE + 24) as usize, randomizer.get_instance_address(index));
+        }
+    }
+
+    assert!(trampolines > 0);
+}
+
+#[test]
+fn padding_leaves_room_for_trampolines() {
+    let firmware = build_firmware(&distant_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(2), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    // gaps as large as the slack of the banks
+    randomizer.set_padding(PaddingPolicy { max_gap: 64, trap: Trap::Udf });
+    let mut trampolines = 0;
+    let mut padded = 0;
+
+    for epoch in 0 .. 32 {
+        randomizer.randomize().unwrap();
+        check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[epoch % 2]);
+        padded += (randomizer.entropy().padding > 0) as usize;
+        trampolines += trampoline_of(&mut randomizer, 1, 0x8fc, 2).or(trampoline_of(&mut randomizer, 2, 0, 1)).is_some() as usize;
+    }
+    assert!(trampolines > 0);
+    assert!(padded > 0);
+}
+
+#[test]
+fn trampolines_exhausting_the_bank_are_reported() {
+    let firmware = build_firmware(&distant_functions());
+    // the objects fill the banks in every order, the trampolines some orders need do not fit
+    let banks = [(0x2001_a000, 0xf40), (0x2002_f000, 0xf40)];
+    let mut platform = HostPlatform::new();
+    platform.map(FLASH_BASE, firmware.flash.clone());
+    for &(base, size) in banks.iter() {
+        platform.map(base, vec![0u8; size]);
+    }
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe { Randomizer::new(platform, SeededRandom::new(1), firmware.metadata, banks, dispatch_tbl) };
+
+    let exhausted = (0 .. 8).filter(|_| match randomizer.randomize() {
+        Ok(()) => false,
+        Err(HarmError::SandboxExhausted { object: TRAMPOLINE_OBJECT, .. }) => true,
+        Err(error) => panic!("unexpected {:?}", error),
+    }).count();
+    assert!(exhausted > 0);
+}
+
+/// A function split into basic blocks: 1 -> 5 -> 6, 5 also branches back to 1
+fn split_function() -> Vec<FunctionSpec> {
+    vec![
+        // 1 - entry block, falls through to 5
+        FunctionSpec { basic_block: false, size: 12, isr: Some(1), branches: vec![(4, 2, 0, RelocKind::BL), (12, 5, 0, RelocKind::B_T4)], callsites: vec![8] },
+        // 2
+        FunctionSpec { basic_block: false, size: 10, isr: None, branches: vec![], callsites: vec![] },
+        // 3
+        FunctionSpec { basic_block: false, size: 6, isr: Some(15), branches: vec![], callsites: vec![] },
+        // 4
+        FunctionSpec { basic_block: false, size: 32, isr: None, branches: vec![(0, 6, 2, RelocKind::BL)], callsites: vec![] },
+        // 5 - second block, branches back to the middle of the entry block, falls through to 6
+        FunctionSpec { basic_block: true, size: 8, isr: None, branches: vec![(0, 1, 4, RelocKind::B_T3), (8, 6, 0, RelocKind::B_T4)], callsites: vec![4] },
+        // 6 - last block, returns
+        FunctionSpec { basic_block: true, size: 6, isr: None, branches: vec![], callsites: vec![] },
+    ]
+}
+
+#[test]
+fn basic_blocks_fall_through() {
+    let firmware = build_firmware(&split_function());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(5), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+
+    for epoch in 0 .. 4 {
+        randomizer.randomize().unwrap();
+        check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[epoch % 2]);
+
+        // a branch to the next block is appended to the blocks falling through
+        for &(block, size, next) in [(1, 12, 5), (5, 8, 6)].iter() {
+            let src_addr = randomizer.get_instance_address(block) + size;
+            let dst_addr = randomizer.get_instance_address(next);
+            assert_eq!(randomizer.platform().read32(src_addr), adjustment::encode_fall_through(src_addr, dst_addr).unwrap());
+
+            // the appended branch belongs to the block
+            let (object, offset) = randomizer.lookup(src_addr + 2).unwrap();
+            assert_eq!((object.get_object().index as usize, offset), (block, size + 2));
+        }
+
+        // no branch is appended to the last block
+        let last = randomizer.get_instance_address(6);
+        assert_ne!(randomizer.lookup(last + 6).map(|(object, _)| object.get_object().index), Some(6));
+    }
+}
+
+/// References into the middle of functions, some of them inside their own function
+fn interior_references() -> Vec<FunctionSpec> {
+    vec![
+        // 1 - reset handler
+        FunctionSpec {
+            basic_block: false,
+            size: 40,
+            isr: Some(1),
+            branches: vec![
+                // call into the body of 2, tail-call the shared epilogue of 3
+                (0, 2, 6, RelocKind::BL),
+                (4, 3, 10, RelocKind::B_T4),
+                (8, 2, 6, RelocKind::B_T3),
+                // loop and switch targets inside 1 itself
+                (12, 1, 30, RelocKind::B_T2),
+                (14, 1, 20, RelocKind::CBZ),
+                // address of the epilogue of 3 and of a label of 1, with the Thumb bit
+                (16, 3, 11, RelocKind::MOVW),
+                (20, 3, 11, RelocKind::MOVT),
+                (24, 1, 33, RelocKind::MOVW),
+                (28, 1, 33, RelocKind::MOVT),
+            ],
+            callsites: vec![4],
+        },
+        // 2
+        FunctionSpec { basic_block: false, size: 16, isr: None, branches: vec![], callsites: vec![] },
+        // 3 - epilogue at offset 10
+        FunctionSpec { basic_block: false, size: 20, isr: None, branches: vec![(2, 2, 8, RelocKind::B_T2)], callsites: vec![] },
+    ]
+}
+
+#[test]
+fn branches_into_interiors_land_on_target() {
+    let mut firmware = build_firmware(&interior_references());
+
+    // the references inside object 1 are real instructions, they are not rewritten
+    let base = firmware.metadata.objects[1].get_object().get_address();
+    let intra = [(12, 30, RelocKind::B_T2, 0xe000), (14, 20, RelocKind::CBZ, 0xb100)];
+    for &(offset, target, kind, template) in intra.iter() {
+        let code = adjustment::encode(kind, template, base + offset, base + target).unwrap() as u16;
+        let at = base - FLASH_BASE + offset;
+        firmware.flash[at .. at + 2].copy_from_slice(&code.to_le_bytes());
+    }
+
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(9), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    randomizer.set_padding(PaddingPolicy { max_gap: 64, trap: Trap::Udf });
+
+    for epoch in 0 .. 8 {
+        randomizer.randomize().unwrap();
+        check_layout(&mut randomizer, &firmware, SANDBOX_REGIONS[epoch % 2]);
+
+        let address = randomizer.get_instance_address(1);
+        for &(offset, target, kind, _) in intra.iter() {
+            let at = base - FLASH_BASE + offset;
+            assert_eq!(randomizer.platform().read(address + offset, 2), firmware.flash[at .. at + 2].to_vec());
+            let code = randomizer.platform().read32(address + offset);
+            assert_eq!(decoder::decode(kind, code, address + offset), address + target);
+        }
+    }
+}
+
+#[cfg(feature = "verify-relocations")]
+#[test]
+#[should_panic(expected = "at offset 4 of object 1 lands on")]
+fn verify_relocations_catches_a_wrong_target() {
+    let firmware = build_firmware(&sample_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(8), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+
+    randomizer.shuffle().unwrap();
+    randomizer.ref_adjust().unwrap();
+
+    // call the middle of object 2 instead of its start
+    let src_addr = randomizer.get_staged_address(1) + 4;
+    let code = randomizer.platform().read32(src_addr);
+    let wrong = adjustment::encode(RelocKind::BL, code, src_addr, randomizer.get_staged_address(2) + 2).unwrap();
+    randomizer.platform().write32(src_addr, wrong);
+
+    randomizer.verify_relocations();
+}
+
+#[test]
+fn too_small_sandbox_is_reported() {
+    let firmware = build_firmware(&distant_functions());
+    let mut platform = host_platform(&firmware);
+    let banks = [(0x3000_0000, 0x800), (0x3001_0000, 0x800)];
+    for &(base, size) in banks.iter() {
+        platform.map(base, vec![0u8; size]);
+    }
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe { Randomizer::new(platform, SeededRandom::new(4), firmware.metadata, banks, dispatch_tbl) };
+
+    match randomizer.randomize() {
+        Err(HarmError::SandboxExhausted { needed, left, .. }) => assert!(needed > left),
+        result => panic!("unexpected {:?}", result),
+    }
+    // nothing was committed
+    assert_eq!(randomizer.platform().vtor, None);
+}
+
+#[test]
+fn bad_metadata_is_reported() {
+    let firmware = build_firmware(&[
+        FunctionSpec { basic_block: false, size: 8, isr: Some(1), branches: vec![(0, 7, 0, RelocKind::BL)], callsites: vec![] },
+    ]);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(4), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    assert!(matches!(randomizer.randomize(), Err(HarmError::MetadataInconsistency { object: 1, .. })));
+
+    // a branch into the vector table
+    let firmware = build_firmware(&[
+        FunctionSpec { basic_block: false, size: 8, isr: Some(1), branches: vec![(4, 0, 0, RelocKind::BL)], callsites: vec![] },
+    ]);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(4), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    assert_eq!(randomizer.randomize(), Err(HarmError::InvalidRelocation { object: 1, offset: 4 }));
+}
+
+#[test]
+fn only_the_running_bank_is_executable() {
+    let firmware = build_firmware(&sample_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(7), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    assert_eq!(randomizer.platform().executable, None);
+
+    randomizer.randomize().unwrap();
+    assert_eq!(randomizer.platform().executable, Some(SANDBOX_REGIONS[0]));
+    assert_eq!(randomizer.platform().original, None);
+
+    // the bank being built stays out of reach until the commit
+    randomizer.shuffle().unwrap();
+    randomizer.ref_adjust().unwrap();
+    assert_eq!(randomizer.platform().executable, Some(SANDBOX_REGIONS[0]));
+    randomizer.commit();
+    assert_eq!(randomizer.platform().executable, Some(SANDBOX_REGIONS[1]));
+
+    // the original firmware runs from the flash, the sandbox is of no use
+    randomizer.commit_original().unwrap();
+    assert_eq!(randomizer.platform().executable, None);
+    let last = firmware.metadata.objects.last().unwrap().get_object();
+    assert_eq!(randomizer.platform().original, Some((FLASH_BASE, last.get_address() + last.get_size() - FLASH_BASE)));
+}
+
+/// A function loading from two literal pools moved out of it
+fn literal_pools() -> Vec<FunctionSpec> {
+    vec![
+        // 1 - reset handler, the address of 3 in a register and a word of 4 loaded
+        FunctionSpec {
+            basic_block: false,
+            size: 24,
+            isr: Some(1),
+            branches: vec![(0, 2, 0, RelocKind::BL), (4, 3, 0, RelocKind::MOVW), (8, 3, 0, RelocKind::MOVT), (12, 4, 4, RelocKind::LDR_W)],
+            callsites: vec![4],
+        },
+        // 2
+        FunctionSpec { basic_block: false, size: 10, isr: None, branches: vec![], callsites: vec![] },
+        // 3, 4 - literal pools
+        FunctionSpec { basic_block: false, size: 20, isr: None, branches: vec![], callsites: vec![] },
+        FunctionSpec { basic_block: false, size: 8, isr: None, branches: vec![], callsites: vec![] },
+    ]
+}
+
+#[test]
+fn literal_pools_are_placed_in_the_data_area() {
+    let firmware = with_data(build_firmware(&literal_pools()), &[3, 4]);
+    assert_eq!(firmware.metadata.get_data_size(), 64);
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(11), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    randomizer.set_padding(PaddingPolicy { max_gap: 64, trap: Trap::Udf });
+    randomizer.set_protection(CodeProtection::ExecuteOnly).unwrap();
+
+    for epoch in 0 .. 4 {
+        randomizer.randomize().unwrap();
+        let bank = SANDBOX_REGIONS[epoch % 2];
+        check_layout(&mut randomizer, &firmware, bank);
+
+        // the data area is never executable
+        assert_eq!(randomizer.platform().executable, Some((bank.0, bank.1 - 64)));
+        assert_eq!(randomizer.platform().data_size, 64);
+    }
+}
+
+#[test]
+fn execute_only_code_cannot_read_itself() {
+    // the literal pool loaded from is left in the code
+    let firmware = with_data(build_firmware(&literal_pools()), &[3]);
+    assert_eq!(validate_metadata(&firmware.metadata, &SANDBOX_REGIONS), Ok(()));
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(11), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    assert_eq!(randomizer.set_protection(CodeProtection::ExecuteOnly), Err(HarmError::MetadataInconsistency { object: 1, reason: "literal pool inside the code" }));
+}
+
+#[test]
+fn execute_only_code_has_no_trampolines() {
+    let firmware = build_firmware(&distant_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(1), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+    randomizer.set_protection(CodeProtection::ExecuteOnly).unwrap();
+
+    // some of the orders need a trampoline (see `out_of_range_branches_use_trampolines`)
+    let failures = (0 .. 8).filter(|_| match randomizer.randomize() {
+        Ok(()) => false,
+        Err(HarmError::BranchOutOfRange { .. }) => true,
+        Err(error) => panic!("unexpected {:?}", error),
+    }).count();
+    assert!(failures > 0);
+}
+
+#[test]
+fn original_firmware_is_executable_without_a_layout() {
+    // the first layout failed, nothing was ever committed
+    let firmware = build_firmware(&sample_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(6), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+
+    randomizer.commit_original().unwrap();
+    assert_eq!(randomizer.platform().vtor, Some(FLASH_BASE));
+    assert_eq!(randomizer.platform().executable, None);
+    let (base, size) = randomizer.platform().original.unwrap();
+    for object in firmware.metadata.objects.iter() {
+        let obj = object.get_object();
+        assert!(obj.get_address() >= base && obj.get_address() + obj.get_size() <= base + size);
+    }
+}
+
+#[test]
+fn original_layout_can_be_committed() {
+    let firmware = build_firmware(&sample_functions());
+    let dispatch_tbl = leak_dispatch_tbl(&firmware);
+    let mut randomizer = unsafe {
+        Randomizer::new(host_platform(&firmware), SeededRandom::new(6), firmware.metadata, SANDBOX_REGIONS, dispatch_tbl)
+    };
+
+    randomizer.randomize().unwrap();
+    randomizer.commit_original().unwrap();
+
+    assert_eq!(randomizer.platform().vtor, Some(FLASH_BASE));
+    for (i, object) in firmware.metadata.objects.iter().enumerate() {
+        assert_eq!(randomizer.get_instance_address(i), object.get_object().get_address());
+    }
+}
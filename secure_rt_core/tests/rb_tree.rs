@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use secure_rt_core::rb_tree::rb_tree::RBTree;
+
+/// xorshift32, good enough to drive the operation sequence
+fn next(seed: &mut u32) -> u32 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 17;
+    *seed ^= *seed << 5;
+    *seed
+}
+
+#[test]
+fn matches_btreemap() {
+    let mut seed = 0xdead_beef;
+    let mut tree = RBTree::new();
+    let mut model = BTreeMap::new();
+
+    for round in 0 .. 20_000 {
+        let key = (next(&mut seed) % 512) as usize;
+
+        match next(&mut seed) % 4 {
+            0 | 1 => assert_eq!(tree.put(key, round), model.insert(key, round)),
+            2 => assert_eq!(tree.remove(&key), model.remove(&key)),
+            _ => {
+                assert_eq!(tree.floor(&key), model.range(..= key).next_back().map(|(k, v)| (*k, *v)));
+                assert_eq!(tree.ceiling(&key), model.range(key ..).next().map(|(k, v)| (*k, *v)));
+            },
+        }
+
+        assert_eq!(tree.get(&key), model.get(&key));
+        assert_eq!(tree.len(), model.len());
+        tree.check_invariants().unwrap();
+        if round % 64 == 0 {
+            assert!(tree.iter().eq(model.iter()));
+        }
+    }
+
+    tree.clear();
+    assert!(tree.is_empty());
+    assert_eq!(tree.iter().next(), None);
+    tree.check_invariants().unwrap();
+}
+
+/// Tree of the keys 0, 10, .. 90, checked after every insertion
+fn filled() -> RBTree<usize, usize> {
+    let mut tree = RBTree::default();
+    for key in [50, 20, 80, 10, 30, 70, 90, 0, 40, 60].iter() {
+        assert_eq!(tree.put(*key, *key + 1), None);
+        tree.check_invariants().unwrap();
+    }
+    tree
+}
+
+#[test]
+fn remove_root() {
+    let mut tree = RBTree::new();
+    tree.put(1, 'a');
+    assert_eq!(tree.remove(&1), Some('a'));
+    tree.check_invariants().unwrap();
+    assert!(tree.is_empty());
+    assert_eq!(tree.iter().next(), None);
+
+    // a balanced tree of 3 keys has the middle one at its root, with two children
+    for order in [[1, 2, 3], [2, 1, 3], [3, 2, 1]].iter() {
+        let mut tree = RBTree::new();
+        for key in order.iter() {
+            tree.put(*key, *key * 10);
+            tree.check_invariants().unwrap();
+        }
+
+        assert_eq!(tree.remove(&2), Some(20));
+        tree.check_invariants().unwrap();
+        assert!(tree.iter().eq([(&1, &10), (&3, &30)].iter().copied()));
+    }
+}
+
+#[test]
+fn remove_every_node() {
+    // every shape of removal, including nodes with two children and the root
+    for key in (0 .. 100).step_by(10) {
+        let mut tree = filled();
+        assert_eq!(tree.remove(&key), Some(key + 1));
+        tree.check_invariants().unwrap();
+        assert_eq!(tree.len(), 9);
+        assert_eq!(tree.get(&key), None);
+        assert!(tree.iter().map(|(k, _)| *k).eq((0 .. 100).step_by(10).filter(|k| *k != key)));
+    }
+
+    let mut tree = filled();
+    for key in [50, 20, 80, 10, 30, 70, 90, 0, 40, 60].iter() {
+        assert_eq!(tree.remove(key), Some(*key + 1));
+        tree.check_invariants().unwrap();
+    }
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn floor_and_ceiling_out_of_range() {
+    let mut tree = filled();
+    assert_eq!(tree.remove(&0), Some(1));
+    tree.check_invariants().unwrap();
+
+    assert_eq!(tree.floor(&5), None);
+    assert_eq!(tree.ceiling(&5), Some((10, 11)));
+    assert_eq!(tree.floor(&0), None);
+    assert_eq!(tree.ceiling(&0), Some((10, 11)));
+
+    assert_eq!(tree.floor(&95), Some((90, 91)));
+    assert_eq!(tree.ceiling(&95), None);
+    assert_eq!(tree.floor(&usize::MAX), Some((90, 91)));
+    assert_eq!(tree.ceiling(&usize::MAX), None);
+
+    assert_eq!(tree.floor(&10), Some((10, 11)));
+    assert_eq!(tree.ceiling(&90), Some((90, 91)));
+
+    let empty: RBTree<usize, usize> = RBTree::new();
+    assert_eq!(empty.floor(&5), None);
+    assert_eq!(empty.ceiling(&5), None);
+}
+
+#[test]
+fn reuse_after_clear() {
+    let mut tree = filled();
+    tree.clear();
+    tree.check_invariants().unwrap();
+    assert!(tree.is_empty());
+    assert_eq!(tree.iter().next(), None);
+
+    for key in [7, 3, 9, 1, 5].iter() {
+        assert_eq!(tree.put(*key, *key), None);
+        tree.check_invariants().unwrap();
+    }
+    assert_eq!(tree.put(5, 50), Some(5));
+    tree.check_invariants().unwrap();
+    assert!(tree.iter().eq([(&1, &1), (&3, &3), (&5, &50), (&7, &7), (&9, &9)].iter().copied()));
+    assert_eq!(tree.len(), 5);
+}
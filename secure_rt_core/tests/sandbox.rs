@@ -0,0 +1,37 @@
+mod common;
+
+use common::reason;
+use secure_rt_core::{check_banks, HarmError};
+
+/// Non-secure alias of the LPC55S69 RAM
+const NON_SECURE: [(usize, usize); 1] = [(0x2000_0000, 0x1000_0000)];
+
+#[test]
+fn disjoint_banks_pass() {
+    assert_eq!(check_banks(&[(0x2001a000, 0x2a00), (0x2002f000, 0x2a00)], &NON_SECURE), Ok(()));
+    // banks may sit in different regions, back to back
+    let regions = [(0x2001_0000, 0x1000), (0x2004_0000, 0x1000)];
+    assert_eq!(check_banks(&[(0x2004_0000, 0x1000), (0x2001_0000, 0x1000)], &regions), Ok(()));
+    assert_eq!(check_banks(&[(0x2001_0000, 0x800), (0x2001_0800, 0x800)], &regions), Ok(()));
+}
+
+#[test]
+fn misplaced_banks_are_rejected() {
+    assert_eq!(reason(check_banks(&[(0x2001a010, 0x2a00)], &NON_SECURE)), "not aligned to the SAU and MPU granularity");
+    assert_eq!(reason(check_banks(&[(0x2001a000, 0x2a10)], &NON_SECURE)), "not aligned to the SAU and MPU granularity");
+    assert_eq!(reason(check_banks(&[(0x2001a000, 0)], &NON_SECURE)), "empty bank");
+    assert_eq!(reason(check_banks(&[(0x2001a000, 0x2a00), (0x2001c000, 0x2a00)], &NON_SECURE)), "overlaps another bank");
+    // secure RAM, and a bank running past the end of its region
+    assert_eq!(reason(check_banks(&[(0x3001_0000, 0x2a00)], &NON_SECURE)), "not within a non-secure region");
+    assert_eq!(reason(check_banks(&[(0x2001_0800, 0x1000)], &[(0x2001_0000, 0x1000)])), "not within a non-secure region");
+    assert_eq!(reason(check_banks(&[(0x2001a000, 0x2a00)], &[])), "not within a non-secure region");
+}
+
+#[test]
+fn offending_bank_is_reported() {
+    let banks = [(0x2001a000, 0x2a00), (0x3002f000, 0x2a00)];
+    match check_banks(&banks, &NON_SECURE) {
+        Err(HarmError::InvalidSandbox { base, size, .. }) => assert_eq!((base, size), banks[1]),
+        other => panic!("expected an invalid sandbox, got {:?}", other),
+    }
+}
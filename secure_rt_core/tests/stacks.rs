@@ -0,0 +1,91 @@
+mod common;
+
+use common::reason;
+use secure_rt_core::stacks::STACK_ALIGN;
+use secure_rt_core::{HeapPolicy, SeededRandom, StackLayout, StackPolicy};
+
+const NON_SECURE: [(usize, usize); 1] = [(0x2000_0000, 0x1000_0000)];
+const BANKS: [(usize, usize); 2] = [(0x2001a000, 0x2a00), (0x2002f000, 0x2a00)];
+
+const POLICY: StackPolicy = StackPolicy {
+    window: (0x2000_c000, 0x4000),
+    main_size: 0x1000,
+    process_size: 0x800,
+    heap: Some(HeapPolicy { symbol: 0x2000_bffc, window: (0x2000_8000, 0x1000) }),
+};
+
+#[test]
+fn stacks_are_drawn_from_the_window() {
+    assert_eq!(POLICY.check(&NON_SECURE, &BANKS), Ok(()));
+    let (base, size) = POLICY.window;
+    let mut rng = SeededRandom::new(3);
+    let mut tops = Vec::new();
+
+    for _ in 0 .. 64 {
+        let stacks = POLICY.place(&mut rng);
+
+        assert_eq!(stacks.msp - stacks.msplim, POLICY.main_size);
+        assert_eq!(stacks.psp - stacks.psplim, POLICY.process_size);
+        for &address in [stacks.msp, stacks.msplim, stacks.psp, stacks.psplim].iter() {
+            assert_eq!(address % STACK_ALIGN, 0);
+            assert!(address >= base && address <= base + size);
+        }
+        // the stacks do not overlap
+        assert!(stacks.msp <= stacks.psplim || stacks.psp <= stacks.msplim);
+
+        let (symbol, heap) = stacks.heap.unwrap();
+        assert_eq!(symbol, 0x2000_bffc);
+        assert_eq!(heap % STACK_ALIGN, 0);
+        assert!((0x2000_8000 .. 0x2000_9000).contains(&heap));
+
+        tops.push(stacks.msp);
+    }
+
+    tops.sort_unstable();
+    tops.dedup();
+    assert!(tops.len() > 32);
+}
+
+#[test]
+fn process_stack_is_optional() {
+    let policy = StackPolicy { process_size: 0, heap: None, ..POLICY };
+    let mut rng = SeededRandom::new(5);
+
+    for _ in 0 .. 16 {
+        let stacks = policy.place(&mut rng);
+        assert_eq!((stacks.psp, stacks.psplim, stacks.heap), (0, 0, None));
+        assert_eq!(stacks.msp - stacks.msplim, policy.main_size);
+    }
+
+    // the whole window for the main stack
+    let policy = StackPolicy { main_size: 0x4000, ..policy };
+    assert_eq!(policy.place(&mut rng), StackLayout { msp: 0x2001_0000, msplim: 0x2000_c000, psp: 0, psplim: 0, heap: None });
+}
+
+#[test]
+fn stack_top_follows_the_stack_pointer() {
+    let stacks = StackLayout { msp: 0x2000_e000, msplim: 0x2000_d000, psp: 0x2000_c800, psplim: 0x2000_c000, heap: None };
+    assert_eq!(stacks.stack_top(0x2000_dfe0), 0x2000_e000);
+    assert_eq!(stacks.stack_top(0x2000_c7e0), 0x2000_c800);
+
+    // without a process stack, everything is on the main stack
+    let fixed = StackLayout::fixed(0x2001_0000);
+    assert_eq!(fixed.stack_top(0x2000_c7e0), 0x2001_0000);
+}
+
+#[test]
+fn broken_windows_are_rejected() {
+    let check = |policy: StackPolicy| reason(policy.check(&NON_SECURE, &BANKS));
+
+    assert_eq!(check(StackPolicy { window: (0x2000_c004, 0x4000), ..POLICY }), "not a non-empty multiple of 8 bytes");
+    assert_eq!(check(StackPolicy { window: (0x1000_c000, 0x4000), ..POLICY }), "not within a non-secure region");
+    assert_eq!(check(StackPolicy { window: (0x2001_9000, 0x4000), ..POLICY }), "overlaps the sandbox");
+    assert_eq!(check(StackPolicy { main_size: 0x3c00, ..POLICY }), "too small for the stacks");
+    assert_eq!(check(StackPolicy { main_size: 0, ..POLICY }), "stack size not a non-empty multiple of 8 bytes");
+
+    let heap = |symbol, window| Some(HeapPolicy { symbol, window });
+    assert_eq!(check(StackPolicy { heap: heap(0x2000_bffc, (0x2000_b000, 0x2000)), ..POLICY }), "overlaps the stacks");
+    assert_eq!(check(StackPolicy { heap: heap(0x2000_bffe, (0x2000_8000, 0x1000)), ..POLICY }), "heap start variable not aligned");
+    assert_eq!(check(StackPolicy { heap: heap(0x2000_8000, (0x2000_8000, 0x1000)), ..POLICY }), "heap start variable inside a window or the sandbox");
+    assert_eq!(check(StackPolicy { heap: heap(0x1000_0000, (0x2000_8000, 0x1000)), ..POLICY }), "not within a non-secure region");
+}
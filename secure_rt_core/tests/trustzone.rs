@@ -0,0 +1,117 @@
+mod common;
+
+use common::reason;
+use secure_rt_core::trustzone::{check_sau, Attribution, MemoryMap, MpcBlock, Region, RegisterRule, SAU_REGIONS};
+use secure_rt_core::HarmError;
+
+/// `FLASH` and `RAM` of `memory.x`
+const SECURE: [(usize, usize); 2] = [(0x1000_0000, 0x1fe00), (0x3001_0000, 0xa000)];
+/// `FLASH_NSC` of `memory.x`
+const VENEERS: (usize, usize) = (0x1001_fe00, 0x200);
+/// Non-secure aliases of the code, RAM and peripherals of the LPC55S69
+const NON_SECURE: [(usize, usize); 3] = [(0x0000_0000, 0x1000_0000), (0x2000_0000, 0x1000_0000), (0x4000_0000, 0x1000_0000)];
+const BANKS: [(usize, usize); 2] = [(0x2001a000, 0x2a00), (0x2002f000, 0x2a00)];
+
+const MAP: MemoryMap = MemoryMap { secure: &SECURE, veneers: VENEERS, non_secure: &NON_SECURE, banks: &BANKS };
+
+/// Non-secure alias of a secure range
+fn alias(&(base, size): &(usize, usize)) -> (usize, usize) {
+    (base & !0x1000_0000, size)
+}
+
+#[test]
+fn sau_regions_follow_the_memory_map() {
+    let regions = MAP.sau_regions().unwrap();
+
+    for (region, &(base, size)) in regions.iter().zip(NON_SECURE.iter()) {
+        assert_eq!(*region, Region { base, size, attribution: Attribution::NonSecure });
+    }
+    assert_eq!(regions[3], Region { base: 0x1001_fe00, size: 0x200, attribution: Attribution::NonSecureCallable });
+    assert_eq!(regions[3].limit(), 0x1001_ffff);
+    assert!(regions[4 ..].iter().all(|region| !region.is_enabled()));
+}
+
+#[test]
+fn broken_memory_maps_are_rejected() {
+    let exposed = [(0x0000_0000, 0x1000_0000), (0x3000_0000, 0x1000_0000)];
+    assert_eq!(reason(MemoryMap { non_secure: &exposed, ..MAP }.sau_regions()), "overlaps the secure runtime");
+    let veneers = [(0x1001_fe00, 0x100)];
+    assert_eq!(reason(MemoryMap { non_secure: &veneers, ..MAP }.sau_regions()), "overlaps the secure runtime");
+    let twice = [(0x2000_0000, 0x1000_0000), (0x2800_0000, 0x100)];
+    assert_eq!(reason(MemoryMap { non_secure: &twice, ..MAP }.sau_regions()), "overlaps another region");
+    let unaligned = [(0x2000_0010, 0x1000)];
+    assert_eq!(reason(MemoryMap { non_secure: &unaligned, ..MAP }.sau_regions()), "not aligned to the SAU granularity");
+    assert_eq!(reason(MemoryMap { veneers: (0x1001_fe00, 0), ..MAP }.sau_regions()), "empty region");
+    let many = [(0x2000_0000, 0x20); SAU_REGIONS];
+    assert_eq!(reason(MemoryMap { non_secure: &many, ..MAP }.sau_regions()), "too many regions for the SAU");
+}
+
+#[test]
+fn banks_must_be_non_secure() {
+    let banks = [(0x3001_a000, 0x2a00)];
+    match (MemoryMap { banks: &banks, ..MAP }).sau_regions() {
+        Err(HarmError::InvalidSandbox { reason, .. }) => assert_eq!(reason, "not within a non-secure region"),
+        other => panic!("expected an invalid sandbox, got {:?}", other),
+    }
+}
+
+#[test]
+fn sau_read_back_is_compared() {
+    let regions = MAP.sau_regions().unwrap();
+    assert_eq!(check_sau(&regions, &regions), Ok(()));
+
+    // disabled regions only need to stay disabled
+    let mut actual = regions;
+    actual[7] = Region { base: 0x1000, size: 0x20, attribution: Attribution::Secure };
+    assert_eq!(check_sau(&regions, &actual), Ok(()));
+
+    actual[1].size -= 0x20;
+    assert_eq!(check_sau(&regions, &actual), Err(HarmError::SauMismatch { region: 1 }));
+    let mut actual = regions;
+    actual[3].attribution = Attribution::NonSecure;
+    assert_eq!(check_sau(&regions, &actual), Err(HarmError::SauMismatch { region: 3 }));
+    assert_eq!(check_sau(&regions, &regions[.. 4]), Err(HarmError::SauMismatch { region: 4 }));
+}
+
+#[test]
+fn mpc_rules_match_the_secure_memory() {
+    let secure: Vec<_> = SECURE.iter().chain(Some(&VENEERS)).map(alias).collect();
+    let mut out = [RegisterRule::new("", 0, 0); 4];
+
+    let flash = MpcBlock { name: "SEC_CTRL_FLASH_MEM_RULE", rules: 0x500a_c010, base: 0, size: 0xa0000, sector: 0x8000 };
+    let rules = flash.rules(&secure, &BANKS, &mut out).unwrap();
+    assert_eq!(rules.iter().map(|rule| (rule.address, rule.value)).collect::<Vec<_>>(), [(0x500a_c010, 0x3333), (0x500a_c014, 0), (0x500a_c018, 0)]);
+
+    let ram1 = MpcBlock { name: "SEC_CTRL_RAM1_MEM_RULE", rules: 0x500a_c080, base: 0x2001_0000, size: 0x10000, sector: 0x1000 };
+    let rules = ram1.rules(&secure, &BANKS, &mut out).unwrap();
+    assert_eq!(rules.iter().map(|rule| rule.value).collect::<Vec<_>>(), [0x3333_3333, 0x33]);
+
+    let ram2 = MpcBlock { name: "SEC_CTRL_RAM2_MEM_RULE", rules: 0x500a_c0a0, base: 0x2002_0000, size: 0x10000, sector: 0x1000 };
+    assert!(ram2.rules(&secure, &BANKS, &mut out).unwrap().iter().all(|rule| rule.value == 0));
+}
+
+#[test]
+fn banks_cannot_share_a_sector_with_secure_memory() {
+    let secure = [(0x2001_0000, 0xa800)];
+    let ram1 = MpcBlock { name: "SEC_CTRL_RAM1_MEM_RULE", rules: 0x500a_c080, base: 0x2001_0000, size: 0x10000, sector: 0x1000 };
+    let mut out = [RegisterRule::new("", 0, 0); 2];
+
+    match ram1.rules(&secure, &BANKS, &mut out) {
+        Err(HarmError::InvalidMemoryMap { base, reason, .. }) => assert_eq!((base, reason), (0x2001a000, "shares a sector with the secure runtime")),
+        other => panic!("expected an invalid memory map, got {:?}", other),
+    }
+}
+
+#[test]
+fn register_rules_keep_unowned_bits() {
+    let scr = RegisterRule::masked("SCR", 0xe000_ed10, 0x8, 0);
+    assert_eq!(scr.written(0x1e), 0x16);
+    assert_eq!(scr.check(0x16), Ok(()));
+    assert_eq!(scr.check(0x1e), Err(HarmError::RegisterMismatch { address: 0xe000_ed10, expected: 0, actual: 0x8 }));
+
+    // the key of AIRCR reads back as VECTKEYSTAT
+    let aircr = RegisterRule { write_only: 0xffff_0000, ..RegisterRule::masked("AIRCR", 0xe000_ed0c, 0xffff_6008, 0x05fa_4000) };
+    assert_eq!(aircr.written(0xfa05_2008), 0x05fa_4000);
+    assert_eq!(aircr.check(0xfa05_4000), Ok(()));
+    assert!(aircr.check(0xfa05_6000).is_err());
+}
@@ -2,6 +2,8 @@
 #![no_std]
 #![no_main]
 
+extern crate alloc;
+
 // pick a panicking behavior
 // use panic_halt as _; // you can put a breakpoint on `rust_begin_unwind` to catch panics
 // use panic_abort as _; // requires nightly
@@ -9,8 +11,7 @@
 // use panic_semihosting as _; // logs messages to the host stderr; requires a debugger
 use alloc_cortex_m::CortexMHeap;
 
-use cortex_m::{asm, Peripherals};
-use cortex_m::peripheral::sau::{SauRegion, SauRegionAttribute};
+use cortex_m::asm;
 use cortex_m_rt::{entry, exception, pre_init, ExceptionFrame};
 use core::mem::MaybeUninit;
 use core::alloc::Layout;
@@ -19,19 +20,49 @@ use lpc55_hal as hal;
 
 use rtt_target::{rtt_init_print, rprintln};
 
-mod secure_rt_core;
+mod runtime;
+
+use runtime::seed_log::SeedLog;
+use runtime::{FailurePolicy, MetadataSource};
+use secure_rt_core::{CodeProtection, PaddingPolicy, Trap};
 
 extern "C" {
     fn BOARD_Init();
     fn BOARD_EnableSysTick();
-    fn BOARD_InitBootTEE();
     fn BOARD_InitMPU();
 }
 
 #[global_allocator]
 static ALLOCATOR: CortexMHeap = CortexMHeap::empty();
 
-const HEAP_SIZE: usize = 1024;
+/// Secure heap, sized for the built-in metadata by the runtime
+///
+/// Signed metadata flashed in non-secure memory is copied to the heap along with
+/// its dispatch table, add the size of the image when using `MetadataSource::Signed`.
+const HEAP_SIZE: usize = runtime::HEAP_SIZE;
+
+/// Length of a randomization epoch in milliseconds (0 disables re-randomization)
+const EPOCH_MS: u32 = 1000;
+
+/// How the seed of each epoch is recorded, feed it to `harm-layout` to map a crash address
+const SEED_LOG: SeedLog = SeedLog::Memory;
+
+/// Metadata of the non-secure firmware, compiled in from the `metadata` directory
+///
+/// To update the firmware without rebuilding the secure runtime, flash the image written by
+/// `harm-layout --pack metadata.bin` and use
+/// `MetadataSource::Signed { address: 0x70000, public_key: *include_bytes!("../metadata.bin.pub") }`.
+const METADATA: MetadataSource = MetadataSource::Builtin;
+
+/// Random gaps of up to 64 bytes before each object, filled with `UDF` like all unused sandbox space
+const PADDING: PaddingPolicy = PaddingPolicy { max_gap: 64, trap: Trap::Udf };
+
+/// Never run the firmware with a layout the runtime could not build
+const ON_FAILURE: FailurePolicy = FailurePolicy::Halt;
+
+/// Let the firmware read its code, `CodeProtection::ExecuteOnly` needs metadata with `Data` objects
+const PROTECTION: CodeProtection = CodeProtection::Readable;
+
 static mut HEAP: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
 
 #[entry]
@@ -49,8 +80,15 @@ fn main() -> ! {
     // Print out "hello world" to confirm RTT is working
     rprintln!("hello world");
 
-    // Boot the firmware from the sandbox (sandbox is at 0x2001a000, size is 0x2a00 bytes)
-    secure_rt_core::start(0x2001a000, 0x2a00);
+    // Re-randomize the firmware once per epoch (SysTick fires every 1 ms)
+    runtime::scheduler::set_epoch(EPOCH_MS);
+    unsafe { BOARD_EnableSysTick(); }
+
+    // Record the seed of each layout for crash triage
+    runtime::seed_log::set_mode(SEED_LOG);
+
+    // Boot the firmware from the sandbox (banks are configured in `metadata/sandbox.yaml`)
+    runtime::start(METADATA, PADDING, ON_FAILURE, PROTECTION);
 }
 
 #[alloc_error_handler]
@@ -66,14 +104,28 @@ fn panic(_: &PanicInfo) -> ! {
 }
 
 #[exception]
-fn HardFault(_ef: &ExceptionFrame) -> ! {
+fn SysTick() {
+    runtime::scheduler::tick();
+}
+
+/// Hard fault handler, entered through `HardFault` of `board.c` to get `exc_return`
+///
+/// `ef` is the frame on the secure stack selected by cortex-m-rt, it only holds the faulting
+/// PC for faults of the secure world.
+#[no_mangle]
+unsafe extern "C" fn secure_rt_hard_fault(ef: &ExceptionFrame, exc_return: u32) -> ! {
     rprintln!("!!! Hard fault !!!");
+    // map the PC back to the firmware, the layout changes every epoch
+    let pc = runtime::stacked_pc_ns(exc_return).unwrap_or(ef.pc as usize);
+    runtime::report_fault(pc);
     loop {}
 }
 
 #[pre_init]
 unsafe fn before_main() {
-    // Enable TEE and MPU (i.e. Memory Protection Unit)
-    BOARD_InitBootTEE();
+    // Enable TrustZone, derived from `memory.x` and `sandbox.yaml`, a broken memory map leaves
+    // everything secure and `runtime::start` reports it
+    let _ = runtime::trustzone::init();
+    // Enable MPU (i.e. Memory Protection Unit)
     BOARD_InitMPU();
 }
\ No newline at end of file
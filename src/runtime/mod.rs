@@ -0,0 +1,476 @@
+pub mod scheduler;
+pub mod seed_log;
+pub mod trustzone;
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::option::Option;
+use core::ptr;
+use core::slice::from_raw_parts_mut;
+use cortex_m;
+use cortex_m::interrupt;
+use cortex_m::peripheral::sau::SauRegionAttribute;
+use rtt_target::rprintln;
+
+use secure_rt_core::image::{self, PUBLIC_KEY_SIZE};
+use secure_rt_core::randomizer;
+use secure_rt_core::{check_banks, validate_metadata, Callsite, ChaChaDrbg, CodeProtection, HardwareRng, HarmError, Metadata, ObjectKind, PaddingPolicy, Platform, RandomSource, Randomizer, StackLayout, NUM_OF_BANKS};
+
+// tables generated by `build.rs` from the metadata of the firmware
+mod obj_tbl {
+    include!(concat!(env!("OUT_DIR"), "/obj_tbl.rs"));
+}
+mod adj_tbl {
+    include!(concat!(env!("OUT_DIR"), "/adj_tbl.rs"));
+}
+mod ret_tbl {
+    include!(concat!(env!("OUT_DIR"), "/ret_tbl.rs"));
+}
+
+// sandbox banks and stack windows generated by `build.rs` from `sandbox.yaml`
+mod sandbox {
+    include!(concat!(env!("OUT_DIR"), "/sandbox.rs"));
+}
+
+/// Secure heap the runtime needs with the built-in metadata
+///
+/// The randomizer keeps the address index of both banks and its tables, see
+/// `randomizer::heap_size()`. The rest is for the trampolines, the violations
+/// of `validate_metadata()` and the SAU regions read at boot.
+pub const HEAP_SIZE: usize = randomizer::heap_size(obj_tbl::NUM_OF_OBJECTS, ret_tbl::NUM_OF_CALLSITES) + 2 * 1024;
+
+/// The randomizer set up by `start()`, kept alive for re-randomization
+static mut RANDOMIZER: Option<Randomizer<'static, Lpc55, ChaChaDrbg>> = None;
+
+/// The initial stacks the firmware was booted with, their tops bound the stack fixup
+static mut NS_STACKS: StackLayout = StackLayout::fixed(0);
+
+/// Where the runtime takes the metadata of the non-secure firmware from
+#[derive(Clone, Copy)]
+pub enum MetadataSource {
+    /// Tables generated by `build.rs` from the `metadata` directory, compiled into the secure runtime
+    Builtin,
+    /// Image written by `harm-layout --pack`, flashed at `address` and signed by the owner of `public_key`
+    Signed {
+        address: usize,
+        public_key: [u8; PUBLIC_KEY_SIZE],
+    },
+}
+
+/// Dispatch table read by the non-secure callable veneers in `nsc.c`, published by `start()`
+#[no_mangle]
+static mut NSC_DISPATCH_TBL: *const u32 = ptr::null();
+#[no_mangle]
+static mut NSC_DISPATCH_TBL_SZ: u32 = 0;
+
+/// Callsite table read by the non-secure callable veneers in `nsc.c`, published by `start()`
+#[no_mangle]
+static mut NSC_CALLSITE_TBL: *const Callsite = ptr::null();
+#[no_mangle]
+static mut NSC_CALLSITE_TBL_SZ: u32 = 0;
+
+/// What the secure runtime does when it cannot build a layout
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FailurePolicy {
+    /// Stop the device with interrupts disabled
+    Halt,
+    /// Reset the device, the next boot draws a new seed
+    Reboot,
+    /// Run the original firmware from the flash, re-randomization failures keep the running layout
+    BootUnrandomized,
+}
+
+/// The policy given to `start()`
+static mut FAILURE_POLICY: FailurePolicy = FailurePolicy::Halt;
+
+extern "C" {
+    fn get_next_random_number() -> u32;
+    fn BOARD_InitStacksNS(msp: u32, msplim: u32, psp: u32, psplim: u32);
+    fn BOARD_GetStackNS(process: u32) -> u32;
+}
+
+/// LPC55S69 implementation of the secure runtime platform
+pub struct Lpc55;
+
+/// The RNG hardware of LPC55S69, only used to seed the DRBG of the randomizer
+fn hardware_rng() -> HardwareRng<fn() -> u32> {
+    HardwareRng(|| unsafe { get_next_random_number() })
+}
+
+/// Seed of the next epoch, drawn from the RNG hardware
+fn next_epoch_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    hardware_rng().fill_bytes(&mut seed);
+    seed
+}
+
+/// Record `seed` as the seed of the layout `randomizer` builds next
+///
+/// The layout of an epoch only depends on this seed, the metadata and the recorded settings.
+fn record_epoch(randomizer: &Randomizer<Lpc55, ChaChaDrbg>, seed: &[u8; 32]) {
+    seed_log::record(seed, randomizer.next_bank(), randomizer.padding(), randomizer.protection());
+}
+
+/// Region number, base and limit registers of the non-secure MPU, seen from the secure world
+const MPU_NS_RNR: usize = 0xE002ED98;
+const MPU_NS_RBAR: usize = 0xE002ED9C;
+const MPU_NS_RLAR: usize = 0xE002EDA0;
+
+/// Region of the non-secure MPU mapping the whole non-secure flash (see `mpu_config.c`)
+const FLASH_MPU_REGION: u32 = 0;
+
+/// Regions of the non-secure MPU given to the sandbox banks, one per bank (see `mpu_config.c`)
+const SANDBOX_MPU_REGION: u32 = 2;
+
+/// Regions of the non-secure MPU given to the data areas at the end of the banks, one per bank
+const DATA_MPU_REGION: u32 = 4;
+
+/// Read-only at any privilege level, execute-never, and region enable bits
+const MPU_RBAR_RO: u32 = 0b11 << 1;
+const MPU_RBAR_XN: u32 = 1;
+const MPU_RLAR_EN: u32 = 1;
+
+/// Security bit of the response of the `TT` instruction
+const TT_S: u32 = 1 << 22;
+
+/// Secure stack and process stack bits of `EXC_RETURN`
+const EXC_RETURN_S: u32 = 1 << 6;
+const EXC_RETURN_SPSEL: u32 = 1 << 2;
+
+/// Offset of the PC in an exception frame
+const FRAME_PC: usize = 0x18;
+
+impl Platform for Lpc55 {
+    fn set_vtor(&mut self, address: usize) {
+        unsafe {
+            // set the VTOR (Vector Table Offset Register) as randomized address
+            core::ptr::write_volatile(0xE002ED08 as *mut u32, address as u32);
+        }
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    unsafe fn memory<'m>(&self, address: usize, length: usize) -> &'m mut [u8] {
+        from_raw_parts_mut(address as *mut u8, length)
+    }
+
+    fn critical_section<R, F: FnOnce() -> R>(f: F) -> R {
+        interrupt::free(|_| f())
+    }
+
+    fn protect_sandbox(&mut self, banks: [(usize, usize); NUM_OF_BANKS], active: Option<usize>, data_size: usize) {
+        for (i, &(base, size)) in banks.iter().enumerate() {
+            let xn = if active == Some(i) { 0 } else { MPU_RBAR_XN };
+            // metadata failing the validation may not leave room for any code
+            let data = base + size - data_size.min(size);
+            unsafe {
+                core::ptr::write_volatile(MPU_NS_RNR as *mut u32, SANDBOX_MPU_REGION + i as u32);
+                if data == base {
+                    core::ptr::write_volatile(MPU_NS_RLAR as *mut u32, 0);
+                } else {
+                    core::ptr::write_volatile(MPU_NS_RBAR as *mut u32, (base as u32 & !0x1f) | MPU_RBAR_RO | xn);
+                    core::ptr::write_volatile(MPU_NS_RLAR as *mut u32, ((data - 1) as u32 & !0x1f) | MPU_RLAR_EN);
+                }
+
+                // the literal pools are read, never executed
+                core::ptr::write_volatile(MPU_NS_RNR as *mut u32, DATA_MPU_REGION + i as u32);
+                if data_size == 0 {
+                    core::ptr::write_volatile(MPU_NS_RLAR as *mut u32, 0);
+                } else {
+                    core::ptr::write_volatile(MPU_NS_RBAR as *mut u32, (data as u32 & !0x1f) | MPU_RBAR_RO | MPU_RBAR_XN);
+                    core::ptr::write_volatile(MPU_NS_RLAR as *mut u32, ((base + size - 1) as u32 & !0x1f) | MPU_RLAR_EN);
+                }
+            }
+        }
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    fn execute_original(&mut self, _firmware: (usize, usize)) {
+        // the flash region holds the whole firmware, it only lacks the permission to execute it
+        unsafe {
+            core::ptr::write_volatile(MPU_NS_RNR as *mut u32, FLASH_MPU_REGION);
+            let rbar = core::ptr::read_volatile(MPU_NS_RBAR as *const u32);
+            core::ptr::write_volatile(MPU_NS_RBAR as *mut u32, rbar & !MPU_RBAR_XN);
+        }
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    fn is_secure(&self, address: usize, length: usize) -> bool {
+        // the SAU and the IDAU attribute memory by 32 bytes at least
+        length == 0 || ((address & !0x1f) .. address + length).step_by(32).all(|granule| cortex_m::asm::tt(granule as *mut u32) & TT_S != 0)
+    }
+}
+
+fn builtin_metadata() -> Metadata<'static> {
+    Metadata {
+        objects: &obj_tbl::OBJECTS,
+        branches: &adj_tbl::BRANCHES,
+        vectors: &obj_tbl::VECTORS,
+        callsites: &ret_tbl::CALLSITE_TBL,
+    }
+}
+
+/// Top of the non-secure main stack, i.e. the initial MSP_NS in the original vector table
+fn get_ns_stack_top(randomizer: &Randomizer<Lpc55, ChaChaDrbg>) -> Result<usize, HarmError> {
+    match &randomizer.metadata().objects[0] {
+        ObjectKind::VectorTable(ns_vector_tbl) => Ok(randomizer.get_origin_code(ns_vector_tbl).read32(0)? as usize),
+        _ => Err(HarmError::MissingVectorTable),
+    }
+}
+
+/// Stop or reset the device after `error`, following the failure policy
+fn fail_secure(error: HarmError) -> ! {
+    rprintln!("[SECURE] Randomization failed: {}", error);
+
+    if unsafe { FAILURE_POLICY } == FailurePolicy::Reboot {
+        rprintln!("[SECURE] Rebooting");
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+
+    rprintln!("[SECURE] Halting");
+    interrupt::disable();
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Boot the normal world from the vector table at `vector_tbl` with the initial `stacks`
+fn boot_ns(vector_tbl: usize, stacks: StackLayout) -> ! {
+    unsafe {
+        let ns_entry = core::ptr::read_volatile((vector_tbl + 4) as *const u32);
+
+        rprintln!("[SECURE] MSP_NS = 0x{:x} (limit 0x{:x}), VTOR_NS = 0x{:x}", stacks.msp, stacks.msplim, vector_tbl);
+        if stacks.psp != 0 {
+            rprintln!("[SECURE] PSP_NS = 0x{:x} (limit 0x{:x})", stacks.psp, stacks.psplim);
+        }
+        if let Some((symbol, heap)) = stacks.heap {
+            rprintln!("[SECURE] Heap start = 0x{:x}", heap);
+            core::ptr::write_volatile(symbol as *mut u32, heap as u32);
+        }
+        rprintln!("[SECURE] Booting normal world from 0x{:x}", ns_entry);
+
+        NS_STACKS = stacks;
+        BOARD_InitStacksNS(stacks.msp as u32, stacks.msplim as u32, stacks.psp as u32, stacks.psplim as u32);
+
+        // Jump to the firmware and start running it (use BXNS instruction)
+        cortex_m::asm::bx_ns(ns_entry & !1);
+        unreachable!();
+    }
+}
+
+/// Re-randomize the sandbox, called from the `PendSV_hook0` non-secure callable entry
+///
+/// `frame` points to the exception frame stacked by the non-secure PendSV and
+/// `retaddr` is the address in `PendSV_Handler` the hook returns to. The PC
+/// stacked in `frame` and the return addresses above it are moved along with
+/// their object. Returns the (relocated) return address.
+#[no_mangle]
+pub unsafe extern "C" fn secure_rt_pendsv_hook(frame: *mut u32, retaddr: u32) -> u32 {
+    if !scheduler::take_pending() {
+        return retaddr;
+    }
+
+    let randomizer = match RANDOMIZER.as_mut() {
+        Some(randomizer) => randomizer,
+        None => return retaddr,
+    };
+
+    // fresh entropy for every epoch
+    let seed = next_epoch_seed();
+    *randomizer.rng() = ChaChaDrbg::from_seed(seed);
+    record_epoch(randomizer, &seed);
+    let result = randomizer.shuffle().and_then(|_| randomizer.ref_adjust());
+
+    match result {
+        Ok(()) => (),
+        Err(error) if FAILURE_POLICY == FailurePolicy::BootUnrandomized => {
+            // the staged layout is dropped, the running one stays valid
+            rprintln!("[SECURE] Re-randomization failed: {}, keeping the running layout", error);
+            return retaddr;
+        },
+        Err(error) => fail_secure(error),
+    }
+
+    interrupt::free(|_| {
+        randomizer.fixup_stack(frame as usize, NS_STACKS.stack_top(frame as usize));
+        let new_retaddr = randomizer.relocate(retaddr as usize).unwrap_or(retaddr as usize);
+        randomizer.commit();
+        new_retaddr as u32
+    })
+}
+
+
+/// Find the object of the running layout containing `addr`, returns its index and the offset of `addr`
+pub fn lookup(addr: usize) -> Option<(usize, usize)> {
+    let randomizer = unsafe { RANDOMIZER.as_ref()? };
+    randomizer.lookup(addr).map(|(object, offset)| (object.get_object().index as usize, offset))
+}
+
+/// PC stacked by a fault of the non-secure world, `None` for a fault of the secure world
+///
+/// `AIRCR.BFHFNMINS` is clear, so faults of the non-secure world escalate to the
+/// secure HardFault with their frame on `MSP_NS` or `PSP_NS`, as told by `exc_return`.
+pub unsafe fn stacked_pc_ns(exc_return: u32) -> Option<usize> {
+    if exc_return & EXC_RETURN_S != 0 {
+        return None;
+    }
+
+    let sp = BOARD_GetStackNS(exc_return & EXC_RETURN_SPSEL) as usize;
+    Some(ptr::read_volatile((sp + FRAME_PC) as *const u32) as usize)
+}
+
+/// Print the object of the running layout a faulting `pc` lies in, and the matching address of the original firmware
+pub fn report_fault(pc: usize) {
+    let randomizer = match unsafe { RANDOMIZER.as_ref() } {
+        Some(randomizer) => randomizer,
+        None => {
+            rprintln!("[SECURE] Fault at 0x{:x}, no layout is running", pc);
+            return;
+        },
+    };
+
+    match randomizer.lookup(pc) {
+        Some((object, offset)) => {
+            let obj = object.get_object();
+            rprintln!("[SECURE] Fault at 0x{:x}: object {} + 0x{:x}, 0x{:x} in the original firmware", pc, obj.index, offset, obj.address + offset);
+        },
+        None => rprintln!("[SECURE] Fault at 0x{:x}, outside of the sandbox", pc),
+    }
+}
+
+
+/// `(base, size)` of the regions the SAU currently grants to the non-secure world
+fn non_secure_regions() -> Vec<(usize, usize)> {
+    let mut sau = unsafe { cortex_m::Peripherals::steal().SAU };
+
+    if !sau.ctrl.read().get_enable() {
+        // with the SAU disabled, ALLNS makes the whole address space non-secure
+        return if sau.ctrl.read().get_allns() { vec![(0, usize::MAX)] } else { Vec::new() };
+    }
+
+    (0 .. sau.region_numbers())
+        .filter_map(|n| sau.get_region(n).ok())
+        .filter(|region| matches!(region.attribute, SauRegionAttribute::NonSecure))
+        .map(|region| (region.base_address as usize, (region.limit_address - region.base_address) as usize + 1))
+        .collect()
+}
+
+/// Metadata and dispatch table of the firmware to boot, halts or reboots if the metadata cannot be trusted
+fn load_metadata(source: MetadataSource) -> (Metadata<'static>, &'static mut [u32]) {
+    match source {
+        MetadataSource::Builtin => (builtin_metadata(), unsafe { &mut obj_tbl::DISPATCH_TBL[..] }),
+        MetadataSource::Signed { address, public_key } => {
+            rprintln!("[SECURE] Verifying metadata at 0x{:x}", address);
+
+            let metadata = image::load_signed(&Lpc55, address, &public_key).unwrap_or_else(|error| fail_secure(error));
+            (metadata, Box::leak(vec![0u32; metadata.objects.len()].into_boxed_slice()))
+        },
+    }
+}
+
+/// Randomize the firmware into the sandbox and boot the normal world
+///
+/// `source` tells where the metadata of the firmware is. The sandbox banks come from `sandbox.yaml`,
+/// each must be able to hold the whole firmware and lie in a region the SAU made non-secure.
+/// The initial stacks are drawn from the window of `sandbox.yaml`, if any, or taken from the vector table.
+/// `padding` sets the random gaps between objects, the space they leave in a bank bounds them.
+/// `on_failure` decides what happens when a layout cannot be built, at boot or at a later epoch.
+/// `protection` tells whether the code of the firmware must be free of literal pools.
+pub fn start(source: MetadataSource, padding: PaddingPolicy, on_failure: FailurePolicy, protection: CodeProtection) -> ! {
+    unsafe { FAILURE_POLICY = on_failure; }
+
+    rprintln!("[SECURE] Verifying TrustZone configuration");
+
+    // `before_main` set it up, nothing proves it took effect until it is read back
+    if let Err(error) = trustzone::verify() {
+        fail_secure(error);
+    }
+
+    let regions = sandbox::SANDBOX_BANKS;
+    let non_secure = non_secure_regions();
+    rprintln!("[SECURE] Checking sandbox banks");
+
+    // a mismatch with the SAU would let the firmware fault on its own code
+    if let Err(error) = check_banks(&regions, &non_secure) {
+        fail_secure(error);
+    }
+    if let Some(Err(error)) = sandbox::STACKS.map(|policy| policy.check(&non_secure, &regions)) {
+        fail_secure(error);
+    }
+
+    let (metadata, dispatch_tbl) = load_metadata(source);
+
+    rprintln!("[SECURE] Validating metadata");
+
+    if let Err(violations) = validate_metadata(&metadata, &regions) {
+        for violation in violations.iter() {
+            rprintln!("[SECURE] Invalid metadata: {}", violation);
+        }
+        // not even the dispatch table of the original firmware can be trusted, never boot it
+        fail_secure(violations[0]);
+    }
+
+    // the veneers trust these tables, the non-secure world must not be able to touch them
+    let tables = [
+        (dispatch_tbl.as_ptr() as usize, dispatch_tbl.len() * 4),
+        (metadata.callsites.as_ptr() as usize, metadata.callsites.len() * core::mem::size_of::<Callsite>()),
+    ];
+    for &(address, length) in tables.iter() {
+        if !Lpc55.is_secure(address, length) {
+            fail_secure(HarmError::ExposedTable { address });
+        }
+    }
+
+    unsafe {
+        NSC_DISPATCH_TBL = dispatch_tbl.as_ptr();
+        NSC_DISPATCH_TBL_SZ = dispatch_tbl.len() as u32;
+        NSC_CALLSITE_TBL = metadata.callsites.as_ptr();
+        NSC_CALLSITE_TBL_SZ = metadata.callsites.len() as u32;
+    }
+
+    let seed = next_epoch_seed();
+    let randomizer = unsafe {
+        RANDOMIZER = Some(Randomizer::new(Lpc55, ChaChaDrbg::from_seed(seed), metadata, regions, dispatch_tbl));
+        RANDOMIZER.as_mut().unwrap()
+    };
+    randomizer.set_padding(padding);
+    if let Err(error) = randomizer.set_protection(protection) {
+        fail_secure(error);
+    }
+    record_epoch(randomizer, &seed);
+
+    rprintln!("[SECURE] Performing initial randomization");
+
+    let result = randomizer.shuffle().and_then(|_| {
+        rprintln!("[SECURE] Layout entropy: {}", randomizer.entropy());
+        rprintln!("[SECURE] Performing reference adjustment");
+        randomizer.ref_adjust()
+    });
+
+    // drawn after the layout from the same seed, which leaves the layout of the epoch unchanged
+    let stacks = match sandbox::STACKS {
+        Some(policy) => policy.place(randomizer.rng()),
+        None => StackLayout::fixed(get_ns_stack_top(randomizer).unwrap_or_else(|error| fail_secure(error))),
+    };
+
+    match result {
+        Ok(()) => randomizer.commit(),
+        Err(error) if on_failure == FailurePolicy::BootUnrandomized => {
+            rprintln!("[SECURE] Randomization failed: {}, booting the original firmware", error);
+            if let Err(error) = randomizer.commit_original() {
+                fail_secure(error);
+            }
+            // nothing to re-randomize and no sandbox to look up
+            let vector_tbl = randomizer.get_instance_address(0);
+            scheduler::set_epoch(0);
+            unsafe { RANDOMIZER = None; }
+            boot_ns(vector_tbl, stacks);
+        },
+        Err(error) => fail_secure(error),
+    }
+
+    boot_ns(randomizer.get_instance_address(0), stacks);
+}
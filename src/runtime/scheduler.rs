@@ -0,0 +1,51 @@
+use core::ptr::write_volatile;
+use cortex_m::interrupt;
+
+/// Interrupt Control and State Register of the non-secure world
+const ICSR_NS: usize = 0xE002ED04;
+const ICSR_PENDSVSET: u32 = 1 << 28;
+
+/// Number of ticks per randomization epoch (0 disables re-randomization)
+static mut EPOCH_TICKS: u32 = 0;
+
+/// Ticks elapsed in the current epoch
+static mut ELAPSED_TICKS: u32 = 0;
+
+/// Set once an epoch expires, cleared when the PendSV hook picks it up
+static mut PENDING: bool = false;
+
+/// Set the length of a randomization epoch in ticks
+pub fn set_epoch(ticks: u32) {
+    interrupt::free(|_| unsafe {
+        EPOCH_TICKS = ticks;
+        ELAPSED_TICKS = 0;
+    });
+}
+
+/// Advance the epoch clock, must be called from the periodic timer interrupt
+///
+/// When the epoch expires, PendSV of the non-secure world is pended so that
+/// the re-randomization takes place in `PendSV_hook0` at the lowest priority.
+pub fn tick() {
+    interrupt::free(|_| unsafe {
+        if EPOCH_TICKS == 0 {
+            return;
+        }
+
+        ELAPSED_TICKS += 1;
+        if ELAPSED_TICKS >= EPOCH_TICKS {
+            ELAPSED_TICKS = 0;
+            PENDING = true;
+            write_volatile(ICSR_NS as *mut u32, ICSR_PENDSVSET);
+        }
+    });
+}
+
+/// Consume a pending re-randomization request
+pub fn take_pending() -> bool {
+    interrupt::free(|_| unsafe {
+        let pending = PENDING;
+        PENDING = false;
+        pending
+    })
+}
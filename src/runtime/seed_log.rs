@@ -0,0 +1,97 @@
+use core::fmt;
+use cortex_m::interrupt;
+use rtt_target::rprintln;
+use secure_rt_core::{CodeProtection, PaddingPolicy};
+
+/// Number of epochs kept in `SEED_HISTORY`
+pub const SEED_HISTORY_LEN: usize = 8;
+
+/// Where the seed of each randomization epoch goes
+///
+/// A seed is enough to rebuild the whole layout of its epoch with `harm-layout`,
+/// so it must never reach the non-secure world. Both destinations are only
+/// readable by a debugger.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SeedLog {
+    /// Do not record seeds
+    Off,
+    /// Print each seed over RTT
+    Rtt,
+    /// Keep the last `SEED_HISTORY_LEN` seeds in `SEED_HISTORY`
+    Memory,
+    /// Both of the above
+    All,
+}
+
+/// Seed of a randomization epoch, epoch 0 is the layout booted by `start()`
+///
+/// Along with the seed, it holds everything else the layout depends on that
+/// may differ from one epoch or one build to the next.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct SeedRecord {
+    pub epoch: u32,
+    /// Bank the layout is built in, epochs whose layout was dropped do not switch banks
+    pub bank: u32,
+    /// `PaddingPolicy::max_gap` of the layout
+    pub max_gap: u32,
+    /// 1 if the layout is built for `CodeProtection::ExecuteOnly`
+    pub execute_only: u32,
+    pub seed: [u8; 32],
+}
+
+static mut MODE: SeedLog = SeedLog::Off;
+
+/// Epoch of the next recorded seed
+static mut NEXT_EPOCH: u32 = 0;
+
+/// Ring buffer of the latest seeds, the record of epoch `e` is at `e % SEED_HISTORY_LEN`
+///
+/// Unused slots have an epoch of `u32::MAX`.
+#[no_mangle]
+pub static mut SEED_HISTORY: [SeedRecord; SEED_HISTORY_LEN] =
+    [SeedRecord { epoch: u32::MAX, bank: 0, max_gap: 0, execute_only: 0, seed: [0; 32] }; SEED_HISTORY_LEN];
+
+/// Choose how seeds are recorded, must be called before `start()`
+pub fn set_mode(mode: SeedLog) {
+    interrupt::free(|_| unsafe { MODE = mode });
+}
+
+/// Record `seed` as the seed of the next epoch, whose layout is built in `bank`, returns that epoch
+pub fn record(seed: &[u8; 32], bank: usize, padding: PaddingPolicy, protection: CodeProtection) -> u32 {
+    let execute_only = protection == CodeProtection::ExecuteOnly;
+    let (mode, epoch) = interrupt::free(|_| unsafe {
+        let epoch = NEXT_EPOCH;
+        NEXT_EPOCH = NEXT_EPOCH.wrapping_add(1);
+
+        if MODE == SeedLog::Memory || MODE == SeedLog::All {
+            SEED_HISTORY[epoch as usize % SEED_HISTORY_LEN] = SeedRecord {
+                epoch,
+                bank: bank as u32,
+                max_gap: padding.max_gap as u32,
+                execute_only: execute_only as u32,
+                seed: *seed,
+            };
+        }
+        (MODE, epoch)
+    });
+
+    // the arguments `harm-layout` needs to rebuild the layout
+    if mode == SeedLog::Rtt || mode == SeedLog::All {
+        rprintln!("[SECURE] Epoch {}: --seed {} --staging {} --max-gap {}{}",
+                  epoch, Hex(seed), bank, padding.max_gap, if execute_only { " --execute-only" } else { "" });
+    }
+    epoch
+}
+
+/// Formats bytes as a hex string, the format `harm-layout --seed` expects
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
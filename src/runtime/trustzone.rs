@@ -0,0 +1,219 @@
+//! TrustZone configuration of the LPC55S69
+//!
+//! The SAU regions and the rules of the memory protection checkers (MPC) of
+//! the AHB secure controller are derived from `memory.x` and `sandbox.yaml`,
+//! the peripheral, master, interrupt and lock rules are those of the board.
+//! `init()` applies the configuration from `before_main`, before `.data` and
+//! `.bss` are initialized, so nothing here may allocate or use statics.
+//! `verify()` reads every register back once the runtime is up.
+
+use cortex_m::peripheral::sau::{SauRegion, SauRegionAttribute};
+use cortex_m::Peripherals;
+use rtt_target::rprintln;
+
+use secure_rt_core::trustzone::{check_sau, Attribution, MemoryMap, MpcBlock, Region, RegisterRule, SAU_REGIONS};
+use secure_rt_core::HarmError;
+
+use super::sandbox::SANDBOX_BANKS;
+
+// regions of `memory.x` generated by `build.rs`
+#[allow(dead_code)]
+mod memory {
+    include!(concat!(env!("OUT_DIR"), "/memory.rs"));
+}
+
+/// Code and data of the secure runtime
+const SECURE: [(usize, usize); 2] = [memory::FLASH, memory::RAM];
+
+/// Boot ROM and SRAMX, never given to the non-secure world
+const BOARD_SECURE: [(usize, usize); 2] = [(0x1300_0000, 0x20000), (0x1400_0000, 0x8000)];
+
+/// Non-secure aliases of the code, the RAM and the peripherals
+///
+/// The IDAU makes every address with bit 28 set secure, these are their counterparts.
+const NON_SECURE: [(usize, usize); 3] = [(0x0000_0000, 0x1000_0000), (0x2000_0000, 0x1000_0000), (0x4000_0000, 0x1000_0000)];
+
+/// Bit of the address telling the secure alias from the non-secure one
+const IDAU_SECURE_BIT: usize = 0x1000_0000;
+
+const MEMORY_MAP: MemoryMap<'static> = MemoryMap {
+    secure: &SECURE,
+    veneers: memory::FLASH_NSC,
+    non_secure: &NON_SECURE,
+    banks: &SANDBOX_BANKS,
+};
+
+/// Base address of the AHB secure controller
+const AHB_SECURE_CTRL: usize = 0x500a_c000;
+
+/// Memories behind a memory protection checker, by their non-secure alias
+const MPC_BLOCKS: [MpcBlock; 9] = [
+    MpcBlock { name: "SEC_CTRL_FLASH_MEM_RULE", rules: AHB_SECURE_CTRL + 0x10, base: 0x0000_0000, size: 0xa0000, sector: 0x8000 },
+    MpcBlock { name: "SEC_CTRL_ROM_MEM_RULE", rules: AHB_SECURE_CTRL + 0x20, base: 0x0300_0000, size: 0x20000, sector: 0x1000 },
+    MpcBlock { name: "SEC_CTRL_RAMX_MEM_RULE", rules: AHB_SECURE_CTRL + 0x40, base: 0x0400_0000, size: 0x8000, sector: 0x1000 },
+    MpcBlock { name: "SEC_CTRL_RAM0_MEM_RULE", rules: AHB_SECURE_CTRL + 0x60, base: 0x2000_0000, size: 0x10000, sector: 0x1000 },
+    MpcBlock { name: "SEC_CTRL_RAM1_MEM_RULE", rules: AHB_SECURE_CTRL + 0x80, base: 0x2001_0000, size: 0x10000, sector: 0x1000 },
+    MpcBlock { name: "SEC_CTRL_RAM2_MEM_RULE", rules: AHB_SECURE_CTRL + 0xa0, base: 0x2002_0000, size: 0x10000, sector: 0x1000 },
+    MpcBlock { name: "SEC_CTRL_RAM3_MEM_RULE", rules: AHB_SECURE_CTRL + 0xc0, base: 0x2003_0000, size: 0x10000, sector: 0x1000 },
+    MpcBlock { name: "SEC_CTRL_RAM4_MEM_RULE", rules: AHB_SECURE_CTRL + 0xe0, base: 0x2004_0000, size: 0x4000, sector: 0x1000 },
+    MpcBlock { name: "SEC_CTRL_USB_HS_MEM_RULE", rules: AHB_SECURE_CTRL + 0x170, base: 0x4010_0000, size: 0x4000, sector: 0x1000 },
+];
+
+/// Largest number of rule registers of a memory
+const MAX_MPC_RULES: usize = 4;
+
+/// Peripherals, masters, interrupts and core options, applied in order after the MPC rules
+///
+/// Only `AHB_SECURE_CTRL` and `MAILBOX` are secure, every master is non-secure
+/// and only the mailbox interrupt targets the secure world.
+const RULES: [RegisterRule; 26] = [
+    // peripheral protection checkers, 2 bits per peripheral, `0b11` is secure privileged
+    RegisterRule::new("SEC_CTRL_APB_BRIDGE0_MEM_CTRL0", AHB_SECURE_CTRL + 0x100, 0xfccc_cccc),
+    RegisterRule::new("SEC_CTRL_APB_BRIDGE0_MEM_CTRL1", AHB_SECURE_CTRL + 0x104, 0xfccc_ffcc),
+    RegisterRule::new("SEC_CTRL_APB_BRIDGE0_MEM_CTRL2", AHB_SECURE_CTRL + 0x108, 0xffff_cfff),
+    RegisterRule::new("SEC_CTRL_APB_BRIDGE1_MEM_CTRL0", AHB_SECURE_CTRL + 0x110, 0xffff_cffc),
+    RegisterRule::new("SEC_CTRL_APB_BRIDGE1_MEM_CTRL1", AHB_SECURE_CTRL + 0x114, 0xffcc_fccc),
+    RegisterRule::new("SEC_CTRL_APB_BRIDGE1_MEM_CTRL2", AHB_SECURE_CTRL + 0x118, 0xffcc_ffff),
+    RegisterRule::new("SEC_CTRL_APB_BRIDGE1_MEM_CTRL3", AHB_SECURE_CTRL + 0x11c, 0xffcf_ccfc),
+    RegisterRule::new("SEC_CTRL_AHB_PORT8_SLAVE0_RULE", AHB_SECURE_CTRL + 0x120, 0xcccc_fcff),
+    RegisterRule::new("SEC_CTRL_AHB_PORT8_SLAVE1_RULE", AHB_SECURE_CTRL + 0x124, 0xfffc_fccc),
+    RegisterRule::new("SEC_CTRL_AHB_PORT9_SLAVE0_RULE", AHB_SECURE_CTRL + 0x130, 0xcccc_ffff),
+    RegisterRule::new("SEC_CTRL_AHB_PORT9_SLAVE1_RULE", AHB_SECURE_CTRL + 0x134, 0xcffc_cffc),
+    RegisterRule::new("SEC_CTRL_AHB_PORT10_SLAVE0_RULE", AHB_SECURE_CTRL + 0x140, 0xcccc_ccfc),
+    RegisterRule::new("SEC_CTRL_AHB_PORT10_SLAVE1_RULE", AHB_SECURE_CTRL + 0x144, 0xffff_fffc),
+    // masters
+    RegisterRule::new("MASTER_SEC_LEVEL", AHB_SECURE_CTRL + 0xfd0, 0x8000_0000),
+    RegisterRule::new("MASTER_SEC_ANTI_POL_REG", AHB_SECURE_CTRL + 0xfd4, 0xbfff_ffff),
+    // GPIO state and interrupts of core 1
+    RegisterRule::new("SEC_GPIO_MASK0", AHB_SECURE_CTRL + 0xf80, 0xffff_ffff),
+    RegisterRule::new("SEC_GPIO_MASK1", AHB_SECURE_CTRL + 0xf84, 0xffff_ffff),
+    RegisterRule::new("SEC_CPU_INT_MASK0", AHB_SECURE_CTRL + 0xf90, 0xffff_ffff),
+    RegisterRule::new("SEC_CPU_INT_MASK1", AHB_SECURE_CTRL + 0xf94, 0xffff_ffff),
+    // interrupt target, a set bit makes the interrupt non-secure
+    RegisterRule::new("NVIC_ITNS0", 0xe000_e380, 0x397f_ffff),
+    RegisterRule::new("NVIC_ITNS1", 0xe000_e384, 0x0fff_c47f),
+    // non-secure exceptions get the lower priorities, faults and resets stay secure
+    RegisterRule { write_only: 0xffff_0000, ..RegisterRule::masked("AIRCR", 0xe000_ed0c, 0xffff_6008, 0x05fa_4000) },
+    // no secure-only deep sleep, SecureFault enabled
+    RegisterRule::masked("SCR", 0xe000_ed10, 0x8, 0),
+    RegisterRule::masked("SHCSR", 0xe000_ed24, 1 << 19, 1 << 19),
+    // coprocessors 0, 1 and the FPU open to the non-secure world, none of them powered down
+    RegisterRule::new("NSACR", 0xe000_ed8c, 0xc03),
+    RegisterRule::new("CPPWR", 0xe000_e00c, 0),
+];
+
+/// Rules applied last, they lock the configuration until the next reset
+const LOCKS: [RegisterRule; 5] = [
+    RegisterRule::new("SEC_MASK_LOCK", AHB_SECURE_CTRL + 0xfbc, 0xaaa),
+    // NS_VTOR and the non-secure MPU stay writable, the runtime switches banks through them
+    RegisterRule::new("CPU0_LOCK_REG", AHB_SECURE_CTRL + 0xfec, 0x8000_02aa),
+    RegisterRule::new("CPU1_LOCK_REG", AHB_SECURE_CTRL + 0xff0, 0x8000_000a),
+    // secure checking enabled and the register write-locked
+    RegisterRule::masked("MISC_CTRL_REG", AHB_SECURE_CTRL + 0xffc, 0xfffc, 0xaaa4),
+    RegisterRule::new("MISC_CTRL_DP_REG", AHB_SECURE_CTRL + 0xff8, 0xaaa5),
+];
+
+/// Non-secure alias of a secure range
+fn alias(&(base, size): &(usize, usize)) -> (usize, usize) {
+    (base & !IDAU_SECURE_BIT, size)
+}
+
+/// Secure memory by its non-secure alias, as seen by the memory protection checkers
+fn mpc_secure() -> [(usize, usize); 5] {
+    [alias(&SECURE[0]), alias(&SECURE[1]), alias(&memory::FLASH_NSC), alias(&BOARD_SECURE[0]), alias(&BOARD_SECURE[1])]
+}
+
+/// Call `f` with every MPC rule derived from the memory map
+fn for_each_mpc_rule<F: FnMut(&RegisterRule) -> Result<(), HarmError>>(mut f: F) -> Result<(), HarmError> {
+    let secure = mpc_secure();
+    let mut rules = [RegisterRule::new("", 0, 0); MAX_MPC_RULES];
+
+    for block in MPC_BLOCKS.iter() {
+        for rule in block.rules(&secure, &SANDBOX_BANKS, &mut rules)?.iter() {
+            f(rule)?;
+        }
+    }
+    Ok(())
+}
+
+fn sau_region(region: &Region) -> SauRegion {
+    SauRegion {
+        base_address: region.base as u32,
+        limit_address: region.limit() as u32,
+        attribute: match region.attribution {
+            Attribution::Secure => SauRegionAttribute::Secure,
+            Attribution::NonSecure => SauRegionAttribute::NonSecure,
+            Attribution::NonSecureCallable => SauRegionAttribute::NonSecureCallable,
+        },
+    }
+}
+
+fn apply(rule: &RegisterRule) {
+    unsafe {
+        let current = core::ptr::read_volatile(rule.address as *const u32);
+        core::ptr::write_volatile(rule.address as *mut u32, rule.written(current));
+    }
+}
+
+fn check(rule: &RegisterRule) -> Result<(), HarmError> {
+    rule.check(unsafe { core::ptr::read_volatile(rule.address as *const u32) }).map_err(|error| {
+        rprintln!("[SECURE] {} differs from the configuration", rule.name);
+        error
+    })
+}
+
+/// Program the SAU and the AHB secure controller, then lock them
+///
+/// Nothing is written if the memory map is inconsistent, everything then
+/// stays secure and `verify()` reports the mistake.
+pub fn init() -> Result<(), HarmError> {
+    let regions = MEMORY_MAP.sau_regions()?;
+    for_each_mpc_rule(|_| Ok(()))?;
+
+    let mut sau = unsafe { Peripherals::steal().SAU };
+    for (n, region) in regions.iter().enumerate().filter(|(_, region)| region.is_enabled()) {
+        // the regions are checked against the SAU granularity above
+        let _ = sau.set_region(n as u8, sau_region(region));
+    }
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+    sau.enable();
+
+    for_each_mpc_rule(|rule| {
+        apply(rule);
+        Ok(())
+    })?;
+    RULES.iter().chain(LOCKS.iter()).for_each(apply);
+
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+    Ok(())
+}
+
+/// Read the SAU and the AHB secure controller back and compare them with the configuration
+pub fn verify() -> Result<(), HarmError> {
+    let expected = MEMORY_MAP.sau_regions()?;
+    let mut sau = unsafe { Peripherals::steal().SAU };
+
+    let ctrl = sau.ctrl.read();
+    if !ctrl.get_enable() || ctrl.get_allns() {
+        rprintln!("[SECURE] SAU_CTRL differs from the configuration");
+        return Err(HarmError::SauMismatch { region: 0 });
+    }
+
+    let mut actual = [Region::DISABLED; SAU_REGIONS];
+    for (n, region) in actual.iter_mut().enumerate().take(sau.region_numbers() as usize) {
+        if let Ok(SauRegion { base_address, limit_address, attribute }) = sau.get_region(n as u8) {
+            let attribution = match attribute {
+                SauRegionAttribute::Secure => Attribution::Secure,
+                SauRegionAttribute::NonSecure => Attribution::NonSecure,
+                SauRegionAttribute::NonSecureCallable => Attribution::NonSecureCallable,
+            };
+            *region = Region { base: base_address as usize, size: limit_address.wrapping_sub(base_address) as usize + 1, attribution };
+        }
+    }
+    check_sau(&expected, &actual[.. (sau.region_numbers() as usize).min(SAU_REGIONS)])?;
+
+    for_each_mpc_rule(check)?;
+    RULES.iter().chain(LOCKS.iter()).try_for_each(check)
+}
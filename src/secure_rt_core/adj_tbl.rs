@@ -1,6 +0,0 @@
-use super::adjustment::Branch;
-
-#[no_mangle]
-pub static BRANCHES: [Branch; NUM_OF_BRANCHES] = [];
-
-pub const NUM_OF_BRANCHES: usize = 0;
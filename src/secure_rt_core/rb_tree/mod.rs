@@ -1,2 +0,0 @@
-pub mod rb_tree;
-mod rb_node;
\ No newline at end of file